@@ -0,0 +1,212 @@
+//! Integer fixed-point accounting shared by the crowdfund and the item tokens.
+//!
+//! Amounts are integers in their smallest unit, no decimal or floating point types are used.
+//! Products are computed in 256 bits, so they can't overflow. The rounding rules are:
+//! - A fee is rounded down, so it's never more than the fee rate. The netto is what is left, so
+//!   `netto + fee == amount`.
+//! - When only part of a netto amount fits, the fee kept is proportional to the netto kept and
+//!   rounded down. The rest is the fee leftover, so `kept + leftover` is exactly what was paid.
+//! - A share of a total is rounded down.
+//! - A total is claimed share by share, in whatever order the funders claim. Every claim is rounded
+//!   down and the last claim, the one completing the whole, also gets the dust left by the earlier
+//!   claims. So once everything is claimed the claims add up to the total exactly, and no claim
+//!   gets less than its rounded down share.
+
+// The lints fire inside the macro expansion
+#[allow(clippy::all)]
+mod u256 {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+use u256::U256;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+// a * b / denominator, rounded down
+pub fn mul_div(a: u128, b: u128, denominator: u128) -> u128 {
+    assert!(denominator > 0, "Division by zero.");
+
+    let result = U256::from(a) * U256::from(b) / U256::from(denominator);
+    assert!(result <= U256::from(u128::MAX), "Amount is too large.");
+
+    result.as_u128()
+}
+
+// Split an amount into (netto, fee), for a fee in basis points
+pub fn split_fee(amount: u128, fee_bps: u16) -> (u128, u128) {
+    assert!(u128::from(fee_bps) <= BPS_DENOMINATOR, "Fee can't be more than 10000 bps.");
+
+    let fee = mul_div(amount, u128::from(fee_bps), BPS_DENOMINATOR);
+
+    (amount - fee, fee)
+}
+
+// Split what doesn't fit in the room left into (netto, fee) leftovers
+pub fn split_leftover(netto: u128, fee: u128, room: u128) -> (u128, u128) {
+    if room >= netto {
+        return (0, 0);
+    }
+
+    let fee_kept = mul_div(fee, room, netto);
+
+    (netto - room, fee - fee_kept)
+}
+
+// The part of a total that belongs to a share of the whole
+pub fn share_of(total: u128, share: u128, whole: u128) -> u128 {
+    assert!(share <= whole, "Share is more than the whole.");
+
+    mul_div(total, share, whole)
+}
+
+// The part of a total for one claim, when the total is claimed share by share. The last claimer gets the dust
+pub fn claim_share(total: u128, unclaimed: u128, share: u128, claimed_before: u128, whole: u128) -> u128 {
+    let claimed_after = claimed_before.checked_add(share).expect("Shares are too large.");
+    assert!(claimed_after <= whole, "Share is more than the unclaimed part.");
+
+    if claimed_after == whole {
+        unclaimed
+    } else {
+        share_of(total, share, whole).min(unclaimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: usize = 10_000;
+
+    // Deterministic pseudo-random numbers (SplitMix64), so failures can be reproduced
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        // Values of any magnitude up to max, with the edges more likely
+        fn amount(&mut self, max: u128) -> u128 {
+            let bits = 128 - max.leading_zeros();
+            match self.next_u64() % 8 {
+                0 => 0,
+                1 => max,
+                _ => {
+                    let value = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+                    let limit = 1u128.checked_shl((self.next_u64() % u64::from(bits + 1)) as u32).unwrap_or(u128::MAX);
+                    (value % limit).min(max)
+                }
+            }
+        }
+
+        fn bps(&mut self) -> u16 {
+            (self.next_u64() % (BPS_DENOMINATOR as u64 + 1)) as u16
+        }
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), u128::MAX);
+        assert_eq!(mul_div(7, 3, 2), 10);
+        assert_eq!(mul_div(0, 3, 2), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount is too large.")]
+    fn test_mul_div_too_large() {
+        mul_div(u128::MAX, 2, 1);
+    }
+
+    #[test]
+    fn test_split_fee() {
+        assert_eq!(split_fee(1000, 500), (950, 50));
+        assert_eq!(split_fee(999, 500), (950, 49));
+        assert_eq!(split_fee(u128::MAX, 10_000), (0, u128::MAX));
+    }
+
+    #[test]
+    fn test_split_fee_properties() {
+        let mut rng = Rng(1);
+        for _ in 0..CASES {
+            let (amount, fee_bps) = (rng.amount(u128::MAX), rng.bps());
+            let (netto, fee) = split_fee(amount, fee_bps);
+
+            assert_eq!(netto + fee, amount);
+            assert_eq!(fee, mul_div(amount, u128::from(fee_bps), BPS_DENOMINATOR));
+        }
+    }
+
+    #[test]
+    fn test_split_leftover() {
+        // 60% of the netto fits, so 40% of the fee is given back
+        assert_eq!(split_leftover(1000, 50, 600), (400, 20));
+        assert_eq!(split_leftover(1000, 50, 1000), (0, 0));
+        assert_eq!(split_leftover(1000, 50, 0), (1000, 50));
+        assert_eq!(split_leftover(3, 1, 1), (2, 1));
+    }
+
+    #[test]
+    fn test_split_leftover_properties() {
+        let mut rng = Rng(2);
+        for _ in 0..CASES {
+            let amount = rng.amount(u128::MAX);
+            let (netto, fee) = split_fee(amount, rng.bps());
+            let room = rng.amount(netto);
+            let (netto_leftover, fee_leftover) = split_leftover(netto, fee, room);
+            let (netto_kept, fee_kept) = (netto - netto_leftover, fee - fee_leftover);
+
+            // Nothing is lost or created
+            assert_eq!(netto_kept + netto_leftover + fee_kept + fee_leftover, amount);
+            assert_eq!(netto_kept, room.min(netto));
+
+            // The fee kept is never more than the fee rate of the netto kept
+            if netto > 0 {
+                assert_eq!(fee_kept, mul_div(fee, netto_kept, netto));
+            }
+        }
+    }
+
+    #[test]
+    fn test_share_of_properties() {
+        let mut rng = Rng(3);
+        for _ in 0..CASES {
+            let whole = rng.amount(u128::MAX).max(1);
+            let share = rng.amount(whole);
+            let total = rng.amount(u128::MAX);
+            let part = share_of(total, share, whole);
+
+            assert!(part <= total);
+            assert!(share_of(total, whole - share, whole) + part <= total);
+            assert!(U256::from(part) * U256::from(whole) <= U256::from(total) * U256::from(share));
+        }
+    }
+
+    #[test]
+    fn test_claim_share_properties() {
+        let mut rng = Rng(5);
+        for _ in 0..CASES / 10 {
+            let count = 1 + (rng.next_u64() % 50) as usize;
+            let shares: Vec<u128> = (0..count).map(|_| 1 + rng.amount(u128::MAX / 64)).collect();
+            let whole: u128 = shares.iter().sum();
+            let total = rng.amount(u128::MAX);
+
+            // Claimed in any order, the claims add up to the total
+            let mut unclaimed = total;
+            let mut claimed_before = 0;
+            for share in shares.iter() {
+                let part = claim_share(total, unclaimed, *share, claimed_before, whole);
+                assert!(part >= share_of(total, *share, whole));
+
+                unclaimed -= part;
+                claimed_before += share;
+            }
+            assert_eq!(unclaimed, 0);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Module for restricting who can fund a crowdfund, e.g. to KYC-verified investors.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+pub(crate) const REGISTRY_GAS: u64 = 5 * TGAS;
+const FUND_VERIFIED_CALLBACK_GAS: u64 = 30 * TGAS;
+
+// The accounts allowed to fund a crowdfund. The beneficiary of a funding is checked, not the sender
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Access {
+    // Anyone can fund
+    #[default]
+    Public,
+
+    // Only accounts on the allowlist kept by the compliance role can fund
+    Allowlist,
+
+    // Only accounts verified by an external registry contract can fund
+    Registry { registry_account_id: AccountId }
+}
+
+#[ext_contract(ext_registry)]
+#[allow(dead_code)]
+trait Registry {
+    fn is_verified(&self, account_id: AccountId) -> bool;
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_to_allowlist(&mut self, account_ids: Vec<AccountId>) {
+        self.assert_role(Role::Compliance);
+        for account_id in account_ids.iter() {
+            self.allowlist.insert(account_id);
+        }
+    }
+
+    pub fn remove_from_allowlist(&mut self, account_ids: Vec<AccountId>) {
+        self.assert_role(Role::Compliance);
+        for account_id in account_ids.iter() {
+            self.allowlist.remove(account_id);
+        }
+    }
+
+    pub fn is_allowlisted(&self, account_id: AccountId) -> bool {
+        self.allowlist.contains(&account_id)
+    }
+
+    pub fn get_allowlist(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AccountId> {
+        self.allowlist.iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    // Change who can fund a crowdfund, existing contributions are kept
+    pub fn set_crowdfund_access(&mut self, item_index: u64, access: Access) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.set_access(access);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_crowdfund_access(&self, item_index: u64) -> Access {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_access()
+    }
+
+    // Fund after the registry answered, all checks are done again as the state may have changed
+    #[private]
+    pub fn fund_verified_callback(&mut self, sender_id: AccountId, coin_id: AccountId, amount: U128, msg: String, beneficiary: AccountId, #[callback_result] call_result: Result<bool, PromiseError>) -> U128 {
+        if !matches!(call_result, Ok(true)) {
+            log!("Funding rejected: Account {} is not verified by the registry.", beneficiary);
+            return amount;
+        }
+
+        match self.process_funding(sender_id, coin_id, amount, msg, true) {
+            PromiseOrValue::Value(unused) => unused,
+            PromiseOrValue::Promise(_) => env::panic_str("Funding was already verified."),
+        }
+    }
+}
+
+impl Contract {
+    // Ask the registry whether the beneficiary is verified, and fund in the callback
+    pub(crate) fn verify_and_fund(&self, registry_account_id: AccountId, sender_id: AccountId, coin_id: AccountId, amount: U128, msg: String, beneficiary: AccountId) -> PromiseOrValue<U128> {
+        PromiseOrValue::Promise(
+            ext_registry::ext(registry_account_id)
+                .with_static_gas(Gas(REGISTRY_GAS))
+                .is_verified(beneficiary.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(FUND_VERIFIED_CALLBACK_GAS))
+                        .fund_verified_callback(sender_id, coin_id, amount, msg, beneficiary)
+                )
+        )
+    }
+}
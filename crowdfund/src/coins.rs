@@ -0,0 +1,57 @@
+//! Module for the stablecoins accepted as payment, and normalizing their amounts.
+//!
+//! All crowdfund accounting (goal, progress, fundings & fees) is done in one internal unit
+//! with the contract's number of decimals. Amounts of a coin are converted from and to that unit.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::Serialize;
+use near_sdk::json_types::U128;
+use near_sdk::{AccountId, Balance};
+
+// An NEP-141 coin that is (or was) accepted as payment for crowdfunds
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Coin {
+    // The number of decimals of the coin
+    pub decimals: u8,
+
+    // Whether new fundings in this coin are accepted. Known coins are kept for refunds
+    pub accepted: bool
+}
+
+// Netto and fee amounts funded in a single coin, in the internal unit
+#[derive(BorshDeserialize, BorshSerialize, Default, Clone, Copy)]
+pub struct CoinAmount {
+    pub netto: Balance,
+    pub fee: Balance
+}
+
+// Totals funded in a single coin, in units of the coin itself
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CoinTotalView {
+    pub coin_id: AccountId,
+    pub netto: U128,
+    pub fee: U128
+}
+
+// Convert an amount of a coin to the internal unit. Returns the normalized amount and the dust that can't be represented
+pub fn normalize(amount: Balance, coin_decimals: u8, internal_decimals: u8) -> (Balance, Balance) {
+    if coin_decimals >= internal_decimals {
+        let factor = 10u128.pow(u32::from(coin_decimals - internal_decimals));
+        (amount / factor, amount % factor)
+    } else {
+        let factor = 10u128.pow(u32::from(internal_decimals - coin_decimals));
+        (amount.checked_mul(factor).expect("Amount is too large."), 0)
+    }
+}
+
+// Convert an amount in the internal unit back to the coin. Rounds down when the coin has less decimals
+pub fn denormalize(amount: Balance, coin_decimals: u8, internal_decimals: u8) -> Balance {
+    if coin_decimals >= internal_decimals {
+        let factor = 10u128.pow(u32::from(coin_decimals - internal_decimals));
+        amount.checked_mul(factor).expect("Amount is too large.")
+    } else {
+        let factor = 10u128.pow(u32::from(internal_decimals - coin_decimals));
+        amount / factor
+    }
+}
@@ -0,0 +1,100 @@
+//! Module for looking up what accounts contributed to crowdfunds.
+
+use crate::*;
+
+use near_sdk::serde::Serialize;
+
+// The number of funders returned when no limit is given
+const DEFAULT_FUNDERS_LIMIT: u64 = 50;
+
+// What an account contributed to a crowdfund, amounts in the internal unit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContributionView {
+    pub item_index: u64,
+    pub status: CrowdfundStatus,
+    pub netto: U128,
+    pub fee: U128,
+
+    // Whether the contribution can be refunded now
+    pub refundable: bool,
+
+    // The amount given back when refunded
+    pub refund_amount: U128,
+
+    // Whether the contribution was already refunded
+    pub refunded: bool,
+
+    // Whether the contribution can be withdrawn now
+    pub withdrawable: bool
+}
+
+// What a funder contributed to a crowdfund, amounts in the internal unit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FunderView {
+    pub account_id: AccountId,
+    pub netto: U128,
+    pub fee: U128
+}
+
+#[near_bindgen]
+impl Contract {
+    // Every crowdfund the account funded (or was funded for as beneficiary)
+    pub fn get_contributions(&self, account_id: AccountId) -> Vec<ContributionView> {
+        let item_indices = match self.contributions.get(&account_id) {
+            Some(item_indices) => item_indices.to_vec(),
+            None => return vec!(),
+        };
+
+        item_indices.into_iter()
+            .map(|item_index| {
+                let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+
+                let (netto, fee) = crowdfund.get_contribution(&account_id);
+                let refunded = crowdfund.is_refunded(&account_id);
+
+                ContributionView {
+                    item_index,
+                    status: crowdfund.get_status(),
+                    netto: U128::from(netto),
+                    fee: U128::from(fee),
+                    refundable: crowdfund.is_refundable() && !refunded,
+                    refund_amount: U128::from(crowdfund.get_refund_amount(&account_id)),
+                    refunded,
+                    withdrawable: crowdfund.can_withdraw(&account_id),
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_funders(&self, item_index: u64, from_index: Option<u64>, limit: Option<u64>) -> Vec<FunderView> {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+
+        crowdfund.get_funders(from_index.unwrap_or(0), limit.unwrap_or(DEFAULT_FUNDERS_LIMIT))
+            .into_iter()
+            .map(|account_id| {
+                let (netto, fee) = crowdfund.get_contribution(&account_id);
+
+                FunderView {
+                    account_id,
+                    netto: U128::from(netto),
+                    fee: U128::from(fee),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    // Remember that an account contributed to a crowdfund
+    pub(crate) fn record_contribution(&mut self, account_id: &AccountId, item_index: u64) {
+        let mut item_indices = self.contributions.get(account_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKeys::AccountContributions { account_hash: env::sha256_array(account_id.as_bytes()) })
+        });
+
+        if item_indices.insert(&item_index) {
+            self.contributions.insert(account_id, &item_indices);
+        }
+    }
+}
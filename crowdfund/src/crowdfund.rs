@@ -2,22 +2,33 @@
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::{log, near_bindgen, ext_contract, require, env, AccountId, BorshStorageKey, Balance, CryptoHash, PanicOnDefault, Promise, Gas, PromiseError, PromiseOrValue};
-use near_sdk::collections::{UnorderedMap, UnorderedSet, Vector};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::U128;
 use near_sdk::serde_json::json;
+use near_sdk::serde::{Deserialize, Serialize};
 
 use near_contract_standards::non_fungible_token::metadata::{TokenMetadata};
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 
-use rust_decimal::Decimal;
-use rust_decimal::prelude::*;
+use std::collections::BTreeMap;
 
-const TGAS: u64 = 1_000_000_000_000;
+use wehave_accounting::{share_of, split_fee, split_leftover};
+
+use crate::coins::CoinAmount;
+use crate::access::Access;
+use crate::launch::LaunchSchedule;
+use crate::referrals::{Referral, ReferralAmount, ReversedReferral};
+use crate::limits::ContributionLimits;
+use crate::options::NewItemOptions;
+use crate::events::{CrowdfundEvent, CrowdfundFundedData, CrowdfundGoalReachedData, CrowdfundHardCapReachedData, CrowdfundPositionTransferredData, CrowdfundStatusChangedData, CrowdfundTokenizedData};
+
+pub const TGAS: u64 = 1_000_000_000_000;
 const DEFAULT_TOKEN_SUPPLY: u128 = 1_000_000;
+pub use wehave_accounting::BPS_DENOMINATOR;
 
 #[ext_contract(ext_nft)]
 trait NonFungibleToken {
-    fn nft_mint(&mut self, token_metadata: TokenMetadata, ft_supply: U128, holders: Vec<AccountId>, shares: Vec<U128>);
+    fn nft_mint(&mut self, token_metadata: TokenMetadata, ft_supply: U128, item_index: u64, total_funding: U128);
 }
 
 #[ext_contract(ext_crowdfunds)]
@@ -33,13 +44,13 @@ pub struct Crowdfund {
     // Identifier of this crowdfund
     identifier: u64,
 
-    // The fee % to be paid on crowdfund
-    item_fee_percentage: f64,
+    // The fee to be paid on crowdfund, in basis points (1/100th of a %)
+    item_fee_bps: u16,
 
     // The metadata describing the item
     metadata: TokenMetadata,
 
-    // The goal of funding
+    // The goal of funding: the soft cap, the minimum needed to proceed with the purchase
     goal: u128,
 
     // The fundings performed for this item (account -> USDC funded)
@@ -52,18 +63,151 @@ pub struct Crowdfund {
     progress: u128,
 
     // The status of the crowdfund
+    status: CrowdfundStatus,
+
+    // Timestamp (in nanoseconds) after which the crowdfund no longer accepts funding
+    deadline: u64,
+
+    // Whether the item arrived in the warehouse, so it can be tokenized
+    in_warehouse: bool,
+
+    // Whether fees are given back when the item could not be bought or transported
+    refund_fees: bool,
+
+    // The fundings performed for this item per coin (account -> coin -> netto & fee funded)
+    coin_fundings: UnorderedMap<AccountId, BTreeMap<AccountId, CoinAmount>>,
+
+    // The total funding performed per coin (coin -> netto & fee funded)
+    coin_totals: BTreeMap<AccountId, CoinAmount>,
+
+    // The refunds performed for this item (account -> coin -> amount refunded)
+    refunds: UnorderedMap<AccountId, BTreeMap<AccountId, Balance>>,
+
+    // The part of the refunds that were fees
+    fees_refunded: Balance,
+
+    // The fees withdrawn to the treasury per coin (coin -> fees withdrawn)
+    fees_withdrawn: BTreeMap<AccountId, Balance>,
+
+    // Whether a tokenization (nft_mint) is currently awaiting its result
+    tokenizing: bool,
+
+    // The number of tokenizations that failed
+    failed_tokenizations: u8,
+
+    // The reason the last tokenization failed
+    tokenization_error: Option<String>,
+
+    // The id of the NFT minted for this item, once tokenized
+    token_id: Option<TokenId>,
+
+    // The limits on contributions
+    limits: ContributionLimits,
+
+    // The accounts allowed to fund
+    access: Access,
+
+    // The most funding accepted, at or above the goal to cover e.g. transport and insurance
+    hard_cap: u128,
+
+    // The number of funders whose refund was pushed by a batch, in the order of fundings
+    refunds_processed: u64,
+
+    // How long (in nanoseconds) after funding a funder can withdraw. Without a window, withdrawing is possible until the goal is reached
+    withdrawal_window: Option<u64>,
+
+    // When each funder last funded (account -> timestamp in nanoseconds)
+    funded_at: LookupMap<AccountId, u64>,
+
+    // The netto of withdrawals whose transfer is pending, kept under the hard cap in case they are rolled back
+    withdrawals_pending: u128,
+
+    // The share of the fee credited to the referrer of a funder, in basis points of the fee
+    referral_bps: u16,
+
+    // The referrer of each referred funder, with what its fundings credited per coin (funder -> referral)
+    funder_referrals: LookupMap<AccountId, Referral>,
+
+    // The volume and rewards credited to each referrer (referrer -> coin -> volume & reward)
+    referrals: LookupMap<AccountId, BTreeMap<AccountId, ReferralAmount>>,
+
+    // The rewards claimed by each referrer (referrer -> coin -> reward claimed)
+    referral_claims: LookupMap<AccountId, BTreeMap<AccountId, Balance>>,
+
+    // The rewards credited to referrers per coin, which are not withdrawn to the treasury (coin -> rewards)
+    referral_totals: BTreeMap<AccountId, Balance>,
+
+    // The accounts an operator gave early access to a scheduled launch
+    early_access: LookupSet<AccountId>,
+
+    // When a created crowdfund opens for funding. Without a schedule, an operator starts it
+    launch: Option<LaunchSchedule>
+}
+
+// Layout of a crowdfund before the state was versioned, as deployed on testnet
+#[derive(BorshDeserialize)]
+pub struct LegacyCrowdfund {
+    nft_account_id: AccountId,
+    identifier: u64,
+    item_fee_percentage: f64,
+    metadata: TokenMetadata,
+    goal: u128,
+    fundings: UnorderedMap<AccountId, Balance>,
+    fees_paid: UnorderedMap<AccountId, Balance>,
+    progress: u128,
     status: CrowdfundStatus
 }
 
+impl Crowdfund {
+    // Legacy crowdfunds were funded in a single coin and had no deadline
+    pub fn from_legacy(old: LegacyCrowdfund, coin_id: &AccountId) -> Self {
+        let mut crowdfund = Self::new(old.nft_account_id, old.identifier, old.metadata, old.goal, percentage_to_bps(old.item_fee_percentage), u64::MAX, NewItemOptions::default());
+        crowdfund.fundings = old.fundings;
+        crowdfund.fees_paid = old.fees_paid;
+        crowdfund.progress = old.progress;
+        crowdfund.status = old.status;
+
+        for funder in crowdfund.fundings.keys_as_vector().to_vec() {
+            let (netto, fee) = crowdfund.get_contribution(&funder);
+            crowdfund.record_coin_funding(&funder, coin_id, netto, fee);
+        }
+
+        crowdfund
+    }
+
+    // Legacy crowdfunds stayed Transporting after they were minted, so they could be tokenized again
+    pub fn finish_legacy_tokenization(&mut self, token_id: TokenId) {
+        require!(self.status == CrowdfundStatus::Transporting, "Only a transporting legacy crowdfund can have been minted.");
+
+        log!("Legacy crowdfund {} was minted as token {}", self.identifier, token_id);
+        self.in_warehouse = true;
+        self.token_id = Some(token_id);
+        self.transition(CrowdfundStatus::Tokenized);
+    }
+}
+
+// Convert a fee % (e.g. 4.0) to basis points (e.g. 400)
+pub fn percentage_to_bps(fee_percentage: f64) -> u16 {
+    (fee_percentage * 100.0).round() as u16
+}
+
 // Define storage keys for collections and nested collections
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Fundings { nested_hash: CryptoHash },
     FeesPaid { nested_hash: CryptoHash },
+    Refunds { nested_hash: CryptoHash },
+    CoinFundings { nested_hash: CryptoHash },
+    FundedAt { nested_hash: CryptoHash },
+    FunderReferrals { nested_hash: CryptoHash },
+    Referrals { nested_hash: CryptoHash },
+    ReferralClaims { nested_hash: CryptoHash },
+    EarlyAccess { nested_hash: CryptoHash },
 }
 
 // See smart contract documentation for the meaning of all these
-#[derive(BorshStorageKey, BorshSerialize, BorshDeserialize, PartialEq)]
+#[derive(BorshStorageKey, BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
 pub enum CrowdfundStatus {
     Created,
     Rejected,
@@ -76,19 +220,62 @@ pub enum CrowdfundStatus {
     Tokenized
 }
 
+impl CrowdfundStatus {
+    // The statuses an operator can move a crowdfund to from this status
+    pub fn can_transition_to(&self, next: CrowdfundStatus) -> bool {
+        match self {
+            CrowdfundStatus::Created => matches!(next, CrowdfundStatus::InProgress | CrowdfundStatus::Rejected),
+            CrowdfundStatus::InProgress => matches!(next, CrowdfundStatus::Buying | CrowdfundStatus::OutOfTime | CrowdfundStatus::Rejected),
+            CrowdfundStatus::Buying => matches!(next, CrowdfundStatus::Transporting | CrowdfundStatus::FailedBuying),
+            CrowdfundStatus::Transporting => matches!(next, CrowdfundStatus::Tokenized | CrowdfundStatus::FailedTransporting),
+            _ => false
+        }
+    }
+}
+
 impl Crowdfund {
-    pub fn new(nft_account_id: AccountId, identifier: u64, item_metadata: TokenMetadata, goal: u128, item_fee_percentage: f64) -> Self {
-        Self {
+    // A crowdfund with a launch schedule is created before it opens for funding, otherwise it opens right away.
+    // The fee is resolved by the caller, so the fee of the options is not used
+    pub fn new(nft_account_id: AccountId, identifier: u64, item_metadata: TokenMetadata, goal: u128, item_fee_bps: u16, deadline: u64, options: NewItemOptions) -> Self {
+        let mut crowdfund = Self {
             nft_account_id: nft_account_id,
             identifier: identifier,
-            item_fee_percentage: item_fee_percentage,
+            item_fee_bps,
             metadata: item_metadata,
             goal: goal,
             fundings: UnorderedMap::new(StorageKeys::Fundings { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
             fees_paid: UnorderedMap::new(StorageKeys::FeesPaid { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
             progress: 0u128,
-            status: CrowdfundStatus::InProgress,
-        }
+            status: if options.launch.is_some() { CrowdfundStatus::Created } else { CrowdfundStatus::InProgress },
+            deadline,
+            in_warehouse: false,
+            refund_fees: options.refund_fees.unwrap_or(true),
+            coin_fundings: UnorderedMap::new(StorageKeys::CoinFundings { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            coin_totals: BTreeMap::new(),
+            refunds: UnorderedMap::new(StorageKeys::Refunds { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            fees_refunded: 0,
+            fees_withdrawn: BTreeMap::new(),
+            tokenizing: false,
+            failed_tokenizations: 0,
+            tokenization_error: None,
+            token_id: None,
+            limits: options.limits.unwrap_or_default(),
+            access: options.access.unwrap_or_default(),
+            hard_cap: goal,
+            refunds_processed: 0,
+            withdrawal_window: None,
+            funded_at: LookupMap::new(StorageKeys::FundedAt { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            withdrawals_pending: 0,
+            referral_bps: 0,
+            funder_referrals: LookupMap::new(StorageKeys::FunderReferrals { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            referrals: LookupMap::new(StorageKeys::Referrals { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            referral_claims: LookupMap::new(StorageKeys::ReferralClaims { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+            referral_totals: BTreeMap::new(),
+            launch: options.launch,
+            early_access: LookupSet::new(StorageKeys::EarlyAccess { nested_hash: env::sha256_array(&identifier.to_be_bytes()) }),
+        };
+        crowdfund.set_hard_cap(options.hard_cap.map(|hard_cap| hard_cap.0).unwrap_or(goal));
+        crowdfund
     }
 
     pub fn get_metadata(&self) -> TokenMetadata {
@@ -103,96 +290,891 @@ impl Crowdfund {
         self.goal
     }
 
-    pub fn get_fee_percentage(&self) -> f64 {
-        self.item_fee_percentage
+    pub fn get_fee_bps(&self) -> u16 {
+        self.item_fee_bps
+    }
+
+    pub fn get_hard_cap(&self) -> u128 {
+        self.hard_cap
+    }
+
+    pub fn set_hard_cap(&mut self, hard_cap: u128) {
+        require!(hard_cap >= self.goal, "Hard cap is below the goal.");
+        self.hard_cap = hard_cap;
+    }
+
+    // Whether the crowdfund currently accepts funding, from everyone or from accounts with early access
+    pub fn is_fundable(&self, early_access: bool) -> bool {
+        (self.effective_status() == CrowdfundStatus::InProgress || (early_access && self.is_early_access_open())) && !self.is_funding_closed()
+    }
+
+    pub fn get_launch(&self) -> Option<LaunchSchedule> {
+        self.launch.clone()
+    }
+
+    // Reschedule a crowdfund that hasn't opened yet
+    pub fn set_launch(&mut self, launch: LaunchSchedule) {
+        require!(self.status == CrowdfundStatus::Created, "Only created crowdfunds can be scheduled.");
+        self.launch = Some(launch);
+    }
+
+    pub fn set_early_access(&mut self, account_id: &AccountId, early_access: bool) {
+        if early_access {
+            self.early_access.insert(account_id);
+        } else {
+            self.early_access.remove(account_id);
+        }
+    }
+
+    pub fn has_early_access(&self, account_id: &AccountId) -> bool {
+        self.early_access.contains(account_id)
+    }
+
+    fn is_early_access_open(&self) -> bool {
+        self.effective_status() == CrowdfundStatus::Created && self.launch.as_ref().is_some_and(|launch| launch.is_early_access_open())
+    }
+
+    // Funding closes at the hard cap or the deadline, whichever comes first
+    pub fn is_funding_closed(&self) -> bool {
+        self.get_reserved_progress() >= self.hard_cap || env::block_timestamp() >= self.deadline
+    }
+
+    // The progress with the pending withdrawals, which can still be rolled back
+    fn get_reserved_progress(&self) -> u128 {
+        self.progress + self.withdrawals_pending
+    }
+
+    // Whether funding this amount would surpass the hard cap or a limit of the funder, returning (part of) it
+    pub fn would_overfund(&self, funder: &AccountId, amount: u128) -> bool {
+        let (netto_amount, _) = self.split_netto_and_fee(amount);
+        self.room_for(funder, netto_amount).map_or(true, |room| netto_amount > room)
+    }
+
+    // The netto amount a funder can still add, within the hard cap and the contribution limits
+    fn room_for(&self, funder: &AccountId, netto_amount: u128) -> Result<u128, String> {
+        let remaining_cap = self.hard_cap.saturating_sub(self.get_reserved_progress());
+        let room_for_funder = self.limits.room_for(self.fundings.get(funder).unwrap_or(0), self.fundings.len(), netto_amount, remaining_cap)?;
+
+        Ok(std::cmp::min(remaining_cap, room_for_funder))
+    }
+
+    pub fn get_deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    pub fn get_limits(&self) -> ContributionLimits {
+        self.limits.clone()
+    }
+
+    pub fn set_limits(&mut self, limits: ContributionLimits) {
+        self.limits = limits;
+    }
+
+    pub fn get_access(&self) -> Access {
+        self.access.clone()
+    }
+
+    pub fn set_access(&mut self, access: Access) {
+        self.access = access;
+    }
+
+    pub fn get_status(&self) -> CrowdfundStatus {
+        self.status
     }
 
     pub fn set_status(&mut self, status: CrowdfundStatus) {
+        CrowdfundEvent::CrowdfundStatusChanged(vec![CrowdfundStatusChangedData {
+            item_index: self.identifier,
+            old_status: self.status,
+            new_status: status,
+        }]).emit();
+
         self.status = status;
     }
 
-    // Fund this crowdfund. Any leftover is returned
-    pub fn fund(&mut self, sender_id: AccountId, amount: u128) -> u128 {
-        require!(self.progress < self.goal, "The goal has already been reached for this item.");
+    // Move the crowdfund to a next status, if allowed from the current one
+    pub fn transition(&mut self, next: CrowdfundStatus) {
+        require!(self.status.can_transition_to(next), format!("Crowdfund can't go from {:?} to {:?}.", self.status, next));
+
+        // The pending nft_mint can still succeed, its callback moves the crowdfund on
+        require!(!self.tokenizing, "Item is being tokenized.");
+
+        if next == CrowdfundStatus::Buying {
+            require!(self.progress >= self.goal, "Goal not yet reached.");
+            require!(self.is_funding_closed(), "Funding is still open until the hard cap or the deadline.");
+        }
+
+        // A crowdfund that reached its goal succeeded, it can't be forced into refunds
+        if next == CrowdfundStatus::OutOfTime {
+            require!(self.progress < self.goal, "Goal already reached, the crowdfund can't run out of time.");
+        }
+
+        log!("Crowdfund {} goes from {:?} to {:?}", self.identifier, self.status, next);
+        self.set_status(next);
+    }
+
+    pub fn is_in_warehouse(&self) -> bool {
+        self.in_warehouse
+    }
+
+    // Mark the item as arrived in the warehouse, which allows tokenizing it
+    pub fn set_in_warehouse(&mut self) {
+        require!(self.status == CrowdfundStatus::Transporting, "Item is not being transported.");
+        self.in_warehouse = true;
+    }
+
+    pub fn is_out_of_time(&self) -> bool {
+        self.status == CrowdfundStatus::OutOfTime
+    }
+
+    // The status the crowdfund has now, without saving it: a scheduled crowdfund starts at its start timestamp,
+    // and runs out of time when the deadline passed before reaching the goal. It starts before its deadline, so both can apply
+    pub fn effective_status(&self) -> CrowdfundStatus {
+        let mut status = self.status;
+
+        if status == CrowdfundStatus::Created && self.launch.as_ref().is_some_and(|launch| launch.is_started()) {
+            status = CrowdfundStatus::InProgress;
+        }
+
+        if status == CrowdfundStatus::InProgress && self.progress < self.goal && env::block_timestamp() >= self.deadline {
+            status = CrowdfundStatus::OutOfTime;
+        }
+
+        status
+    }
+
+    // A copy with the effective status, for views. No event is emitted, as the status isn't saved
+    pub fn at_effective_status(mut self) -> Self {
+        self.status = self.effective_status();
+        self
+    }
+
+    // Apply the effective status before changing the crowdfund. Only call this when the crowdfund is saved afterwards,
+    // since the status change is emitted as an event
+    pub fn check_deadline(&mut self) {
+        let status = self.effective_status();
+
+        if status != self.status {
+            log!("Crowdfund {} went from {:?} to {:?} at progress {} of goal {}", self.identifier, self.status, status, self.progress, self.goal);
+            self.set_status(status);
+        }
+    }
+
+    // Fund this crowdfund in the given coin, optionally referred by another account. Accounts with early access can fund
+    // a scheduled crowdfund before it starts. Any leftover is returned
+    pub fn fund(&mut self, sender_id: AccountId, coin_id: AccountId, amount: u128, referrer_id: Option<AccountId>, early_access: bool) -> u128 {
+        self.check_deadline();
+
+        if self.is_out_of_time() {
+            log!("Crowdfund {} is out of time. Returning {}", self.identifier, amount);
+            return amount;
+        }
+
+        require!(self.status == CrowdfundStatus::InProgress || (early_access && self.is_early_access_open()), "This crowdfund is not in progress.");
+        require!(self.get_reserved_progress() < self.hard_cap, "The hard cap has already been reached for this item.");
+
+        if self.is_funding_closed() {
+            log!("Crowdfund {} is closed. Returning {}", self.identifier, amount);
+            return amount;
+        }
 
         // Get the fee amount and the netto funding amount
         let (netto_amount, fee_amount) = self.split_netto_and_fee(amount);
 
-        log!("Funding item {} for {} (Fee: {}) with progress {} and goal {}", self.identifier, netto_amount, fee_amount, self.progress, self.goal);
+        log!("Funding item {} for {} (Fee: {}) with progress {}, goal {} and hard cap {}", self.identifier, netto_amount, fee_amount, self.progress, self.goal, self.hard_cap);
 
         // Get existing funds & fees from sender, or put on 0
-        let mut funded_by_sender: Balance = self.fundings.get(&sender_id).unwrap_or_else(|| 0);
-        let mut fees_paid_by_sender: Balance = self.fees_paid.get(&sender_id).unwrap_or_else(|| 0);
+        let funded_by_sender: Balance = self.fundings.get(&sender_id).unwrap_or_else(|| 0);
+        let fees_paid_by_sender: Balance = self.fees_paid.get(&sender_id).unwrap_or_else(|| 0);
 
-        // Calculate his new contributions
-        let mut new_funded = funded_by_sender.checked_add(netto_amount.into()).unwrap();
-        let mut new_fees_paid = fees_paid_by_sender.checked_add(fee_amount.into()).unwrap();
+        // Contributions that break the limits are returned in full
+        let room = match self.room_for(&sender_id, netto_amount) {
+            Ok(room) => room,
+            Err(reason) => {
+                log!("Funding of item {} returned: {}", self.identifier, reason);
+                return amount;
+            }
+        };
 
-        if (self.progress + netto_amount) >= self.goal {
-            log!("CROWFUNDING GOAL REACHED");
-
-            // if this surpasses the limit, give back what's leftover: calculate how much fees to give back as well
-            let (netto_leftover, fee_leftover) = self.calculate_leftovers(netto_amount, fee_amount);
-            new_funded = new_funded - netto_leftover;
-            new_fees_paid = new_fees_paid - fee_leftover;
+        // If this surpasses the hard cap or the maximum contribution, give back what's leftover: calculate how much fees to give back as well
+        let (netto_leftover, fee_leftover) = if netto_amount > room {
+            self.calculate_leftovers(netto_amount, fee_amount, room)
+        } else {
+            (0, 0)
+        };
 
+        if netto_leftover > 0 {
             log!("He gave too much. Returning netto: {} and fee: {}", netto_leftover, fee_leftover);
-            let leftover = netto_leftover + fee_leftover;
+        }
+
+        let netto_funded = netto_amount - netto_leftover;
+        let fee_paid = fee_amount - fee_leftover;
+
+        // Save the funding that is performed
+        let previous_progress = self.progress;
+        self.fundings.insert(&sender_id, &(funded_by_sender + netto_funded));
+        self.progress = self.progress + netto_funded;
+
+        // Save the fees that are paid
+        self.fees_paid.insert(&sender_id, &(fees_paid_by_sender + fee_paid));
+        self.funded_at.insert(&sender_id, &env::block_timestamp());
+
+        self.record_coin_funding(&sender_id, &coin_id, netto_funded, fee_paid);
+        self.record_referral(&sender_id, referrer_id, &coin_id, netto_funded, fee_paid);
+        self.emit_funded(&sender_id, &coin_id, netto_funded, fee_paid);
+
+        log!("Total for item {} is now at {}", self.identifier, self.progress);
+
+        if previous_progress < self.goal && self.progress >= self.goal {
+            CrowdfundEvent::CrowdfundGoalReached(vec![CrowdfundGoalReachedData {
+                item_index: self.identifier,
+                goal: U128::from(self.goal),
+            }]).emit();
+        }
+
+        if self.hard_cap > self.goal && self.progress == self.hard_cap {
+            CrowdfundEvent::CrowdfundHardCapReached(vec![CrowdfundHardCapReachedData {
+                item_index: self.identifier,
+                hard_cap: U128::from(self.hard_cap),
+            }]).emit();
+        }
+
+        // Return leftover token
+        netto_leftover + fee_leftover
+    }
+
+    pub fn get_refund_fees(&self) -> bool {
+        self.refund_fees
+    }
+
+    // Funders can get their funding back when the item won't be tokenized
+    pub fn is_refundable(&self) -> bool {
+        matches!(self.status, CrowdfundStatus::OutOfTime | CrowdfundStatus::Rejected | CrowdfundStatus::FailedBuying | CrowdfundStatus::FailedTransporting)
+    }
+
+    pub fn is_refunded(&self, funder: &AccountId) -> bool {
+        self.refunds.get(funder).is_some()
+    }
+
+    fn emit_funded(&self, funder: &AccountId, coin_id: &AccountId, netto_amount: u128, fee_amount: u128) {
+        CrowdfundEvent::CrowdfundFunded(vec![CrowdfundFundedData {
+            item_index: self.identifier,
+            account_id: funder.clone(),
+            coin_id: coin_id.clone(),
+            netto: U128::from(netto_amount),
+            fee: U128::from(fee_amount),
+            progress: U128::from(self.progress),
+        }]).emit();
+    }
+
+    // Keep track of what was funded in which coin, so refunds are paid in the same coin
+    fn record_coin_funding(&mut self, funder: &AccountId, coin_id: &AccountId, netto_amount: u128, fee_amount: u128) {
+        let mut funder_coins = self.coin_fundings.get(funder).unwrap_or_default();
+        let funder_coin = funder_coins.entry(coin_id.clone()).or_default();
+        funder_coin.netto += netto_amount;
+        funder_coin.fee += fee_amount;
+        self.coin_fundings.insert(funder, &funder_coins);
+
+        let total_coin = self.coin_totals.entry(coin_id.clone()).or_default();
+        total_coin.netto += netto_amount;
+        total_coin.fee += fee_amount;
+    }
+
+    pub fn get_referral_bps(&self) -> u16 {
+        self.referral_bps
+    }
+
+    pub fn set_referral_bps(&mut self, referral_bps: u16) {
+        self.referral_bps = referral_bps;
+    }
+
+    // Credit the referrer of a funder with the volume and its share of the fee. A funder stays with its first referrer
+    fn record_referral(&mut self, funder: &AccountId, referrer_id: Option<AccountId>, coin_id: &AccountId, netto_amount: u128, fee_amount: u128) {
+        let mut referral = match (self.funder_referrals.get(funder), referrer_id) {
+            (Some(referral), _) => referral,
+            (None, Some(referrer_id)) if &referrer_id != funder => Referral { referrer_id, coins: BTreeMap::new() },
+            _ => return,
+        };
+
+        if netto_amount == 0 {
+            return;
+        }
+
+        let (_, reward) = split_fee(fee_amount, self.referral_bps);
+        log!("Crediting {} with a referral reward of {} for funding {} by {}", referral.referrer_id, reward, netto_amount, funder);
+
+        let funder_coin = referral.coins.entry(coin_id.clone()).or_default();
+        funder_coin.volume += netto_amount;
+        funder_coin.reward += reward;
+        self.funder_referrals.insert(funder, &referral);
+
+        let mut referrer_coins = self.referrals.get(&referral.referrer_id).unwrap_or_default();
+        let referrer_coin = referrer_coins.entry(coin_id.clone()).or_default();
+        referrer_coin.volume += netto_amount;
+        referrer_coin.reward += reward;
+        self.referrals.insert(&referral.referrer_id, &referrer_coins);
+
+        *self.referral_totals.entry(coin_id.clone()).or_default() += reward;
+    }
+
+    // Take back the credit of the referrer for the part of the funding of a funder in a coin that leaves the funder
+    fn reverse_referral(&mut self, funder: &AccountId, coin_id: &AccountId, netto_amount: u128, coin_netto: u128) -> Option<ReversedReferral> {
+        let mut referral = self.funder_referrals.get(funder)?;
+        let funder_coin = referral.coins.get_mut(coin_id)?;
 
-            // Save the funding that is performed (BEFORE! issuing the token)
-            self.fundings.insert(&sender_id, &new_funded);
-            self.progress = self.goal;
+        let volume = share_of(funder_coin.volume, netto_amount, coin_netto);
+        let reward = share_of(funder_coin.reward, netto_amount, coin_netto);
+        funder_coin.volume -= volume;
+        funder_coin.reward -= reward;
+        referral.coins.retain(|_, funder_coin| funder_coin.volume > 0 || funder_coin.reward > 0);
+        self.funder_referrals.insert(funder, &referral);
 
-            // Save the fees that are paid
-            self.fees_paid.insert(&sender_id, &new_fees_paid);
+        let mut referrer_coins = self.referrals.get(&referral.referrer_id).unwrap_or_default();
+        let referrer_coin = referrer_coins.entry(coin_id.clone()).or_default();
+        referrer_coin.volume -= volume;
+        referrer_coin.reward -= reward;
+        self.referrals.insert(&referral.referrer_id, &referrer_coins);
 
-            log!("Initiating tokenization...");
+        *self.referral_totals.entry(coin_id.clone()).or_default() -= reward;
 
-            self.status = CrowdfundStatus::Transporting;
-            // TODO don't immediately tokenize.
-            // Tokenize to be called manually by us when item is acquired in warehouse
+        Some(ReversedReferral {
+            referrer_id: referral.referrer_id,
+            volume: U128::from(volume),
+            reward: U128::from(reward),
+        })
+    }
+
+    // Give back the credit taken back with a withdrawal that failed. A funder that was referred again since then keeps its new referrer
+    fn restore_referral(&mut self, funder: &AccountId, coin_id: &AccountId, reversed: ReversedReferral) {
+        let mut referral = self.funder_referrals.get(funder)
+            .unwrap_or_else(|| Referral { referrer_id: reversed.referrer_id.clone(), coins: BTreeMap::new() });
+        if referral.referrer_id != reversed.referrer_id {
+            log!("{} is referred by {} now, the credit of {} isn't restored", funder, referral.referrer_id, reversed.referrer_id);
+            return;
+        }
+
+        let funder_coin = referral.coins.entry(coin_id.clone()).or_default();
+        funder_coin.volume += reversed.volume.0;
+        funder_coin.reward += reversed.reward.0;
+        self.funder_referrals.insert(funder, &referral);
+
+        let mut referrer_coins = self.referrals.get(&referral.referrer_id).unwrap_or_default();
+        let referrer_coin = referrer_coins.entry(coin_id.clone()).or_default();
+        referrer_coin.volume += reversed.volume.0;
+        referrer_coin.reward += reversed.reward.0;
+        self.referrals.insert(&referral.referrer_id, &referrer_coins);
+
+        *self.referral_totals.entry(coin_id.clone()).or_default() += reversed.reward.0;
+    }
+
+    pub fn get_referrer(&self, funder: &AccountId) -> Option<AccountId> {
+        self.funder_referrals.get(funder).map(|referral| referral.referrer_id)
+    }
+
+    // The volume, reward and claimed reward of a referrer over all coins
+    pub fn get_referral(&self, referrer_id: &AccountId) -> (u128, u128, u128) {
+        let referrer_coins = self.referrals.get(referrer_id).unwrap_or_default();
+        let claims = self.referral_claims.get(referrer_id).unwrap_or_default();
+
+        (
+            referrer_coins.values().map(|amount| amount.volume).sum(),
+            referrer_coins.values().map(|amount| amount.reward).sum(),
+            claims.values().sum(),
+        )
+    }
+
+    // The fees credited to referrers, which don't go to the treasury. They are forfeited when the item won't be tokenized
+    pub fn get_referral_rewards(&self) -> u128 {
+        if self.is_refundable() {
+            return 0;
+        }
+
+        self.referral_totals.values().sum()
+    }
+
+    // Register the claim of the referral rewards of a referrer (BEFORE! transferring). Returns the rewards per coin to transfer
+    pub fn start_referral_claim(&mut self, referrer_id: &AccountId) -> Vec<(AccountId, u128)> {
+        require!(self.status == CrowdfundStatus::Tokenized, "Referral rewards can only be claimed from tokenized items.");
+
+        let referrer_coins = self.referrals.get(referrer_id).unwrap_or_default();
+        let mut claims = self.referral_claims.get(referrer_id).unwrap_or_default();
+
+        let mut rewards: Vec<(AccountId, u128)> = Vec::new();
+        for (coin_id, amount) in &referrer_coins {
+            let claimed = claims.get(coin_id).copied().unwrap_or(0);
+            if amount.reward > claimed {
+                rewards.push((coin_id.clone(), amount.reward - claimed));
+            }
+        }
+
+        require!(!rewards.is_empty(), "No referral rewards to claim.");
+
+        for (coin_id, reward) in &rewards {
+            *claims.entry(coin_id.clone()).or_default() += reward;
+        }
+        self.referral_claims.insert(referrer_id, &claims);
+
+        rewards
+    }
+
+    // Undo the registration of a referral claim in a coin, e.g. when the transfer failed
+    pub fn rollback_referral_claim(&mut self, referrer_id: &AccountId, coin_id: &AccountId, reward: u128) {
+        let mut claims = self.referral_claims.get(referrer_id).unwrap_or_default();
+        *claims.entry(coin_id.clone()).or_default() -= reward;
+        self.referral_claims.insert(referrer_id, &claims);
+    }
+
+    pub fn get_coin_totals(&self) -> BTreeMap<AccountId, CoinAmount> {
+        self.coin_totals.clone()
+    }
+
+    // Fees are given back when the crowdfund ran out of time or was cancelled, or when it is configured to do so
+    fn refunds_include_fee(&self) -> bool {
+        self.refund_fees || matches!(self.status, CrowdfundStatus::OutOfTime | CrowdfundStatus::Rejected)
+    }
+
+    // The amounts per coin a funder gets back: the netto funding, plus the fee if included in refunds
+    pub fn get_refund_amounts(&self, funder: &AccountId) -> Vec<(AccountId, u128)> {
+        let include_fee = self.refunds_include_fee();
+
+        self.coin_fundings.get(funder).unwrap_or_default()
+            .into_iter()
+            .map(|(coin_id, amount)| (coin_id, if include_fee { amount.netto + amount.fee } else { amount.netto }))
+            .filter(|(_, refund_amount)| *refund_amount > 0)
+            .collect()
+    }
+
+    // What was refunded already, plus what is still to be refunded
+    pub fn get_refund_amount(&self, funder: &AccountId) -> u128 {
+        let refunded = self.refunds.get(funder).unwrap_or_default();
+
+        refunded.values().sum::<u128>() + self.get_refund_amounts(funder)
+            .iter()
+            .filter(|(coin_id, _)| !refunded.contains_key(coin_id))
+            .map(|(_, refund_amount)| refund_amount)
+            .sum::<u128>()
+    }
+
+    // Register the refund of a funder (BEFORE! transferring). Returns the amounts per coin to refund
+    pub fn start_refund(&mut self, funder: &AccountId) -> Vec<(AccountId, u128)> {
+        require!(self.is_refundable(), "This crowdfund can't be refunded.");
+
+        let already_refunded = self.is_refunded(funder);
+        let refund_amounts = self.register_refund(funder, usize::MAX);
+
+        require!(!already_refunded || !refund_amounts.is_empty(), "Funding has already been refunded.");
+        require!(!refund_amounts.is_empty(), "Nothing to refund.");
+
+        refund_amounts
+    }
 
-            self.tokenize_item();
+    // Register the refunds of the next funders in line (BEFORE! transferring), up to a number of transfers: one per coin.
+    // A funder paying in more coins than fit is continued in the next batch. Funders that were already refunded are skipped
+    pub fn start_batch_refund(&mut self, max_transfers: u64) -> Vec<(AccountId, Vec<(AccountId, u128)>)> {
+        require!(self.is_refundable(), "This crowdfund can't be refunded.");
+        require!(self.refunds_processed < self.fundings.len(), "All refunds have been processed.");
 
-            // Return leftover token
-            return leftover;
+        let mut transfers = 0;
+        let mut batch = Vec::new();
+
+        while transfers < max_transfers && self.refunds_processed < self.fundings.len() {
+            let funder = self.fundings.keys_as_vector().get(self.refunds_processed).expect("Incorrect funder index!");
+            let refund_amounts = self.register_refund(&funder, (max_transfers - transfers) as usize);
+
+            if self.get_pending_refunds(&funder).is_empty() {
+                self.refunds_processed += 1;
+            }
+
+            if !refund_amounts.is_empty() {
+                transfers += refund_amounts.len() as u64;
+                batch.push((funder, refund_amounts));
+            }
+        }
+
+        batch
+    }
+
+    // The number of funders a batch has not reached yet
+    pub fn get_refunds_remaining(&self) -> u64 {
+        self.fundings.len() - self.refunds_processed
+    }
+
+    // The amounts per coin not refunded yet
+    fn get_pending_refunds(&self, funder: &AccountId) -> Vec<(AccountId, u128)> {
+        let refunded = self.refunds.get(funder).unwrap_or_default();
+
+        self.get_refund_amounts(funder)
+            .into_iter()
+            .filter(|(coin_id, _)| !refunded.contains_key(coin_id))
+            .collect()
+    }
+
+    // Register the refund of at most max_coins of the coins not refunded yet. Returns the amounts per coin to refund
+    fn register_refund(&mut self, funder: &AccountId, max_coins: usize) -> Vec<(AccountId, u128)> {
+        let mut refunded = self.refunds.get(funder).unwrap_or_default();
+        let refund_amounts: Vec<(AccountId, u128)> = self.get_pending_refunds(funder)
+            .into_iter()
+            .take(max_coins)
+            .collect();
+
+        if refund_amounts.is_empty() {
+            return refund_amounts;
+        }
+
+        for (coin_id, refund_amount) in &refund_amounts {
+            refunded.insert(coin_id.clone(), *refund_amount);
+            self.fees_refunded += self.get_refunded_fee(funder, coin_id);
+        }
+        self.refunds.insert(funder, &refunded);
+
+        refund_amounts
+    }
+
+    // Forget the funding in a coin once its refund went through, the refund keeps the amount.
+    // The referral reward on it is forfeited
+    pub fn complete_refund(&mut self, funder: &AccountId, coin_id: &AccountId) {
+        let mut funder_coins = self.coin_fundings.get(funder).unwrap_or_default();
+        if let Some(amount) = funder_coins.remove(coin_id) {
+            if amount.netto > 0 {
+                self.reverse_referral(funder, coin_id, amount.netto, amount.netto);
+            }
+        }
+
+        if funder_coins.is_empty() {
+            self.coin_fundings.remove(funder);
+            self.funded_at.remove(funder);
+            self.funder_referrals.remove(funder);
         } else {
-            // Save the funding that is performed
-            self.fundings.insert(&sender_id, &new_funded);
-            self.progress = self.progress + netto_amount;
+            self.coin_fundings.insert(funder, &funder_coins);
+        }
+    }
+
+    // Undo the registration of a refund in a coin, e.g. when the transfer failed
+    pub fn rollback_refund(&mut self, funder: &AccountId, coin_id: &AccountId) {
+        let mut refunded = self.refunds.get(funder).unwrap_or_default();
+        refunded.remove(coin_id);
+        self.fees_refunded -= self.get_refunded_fee(funder, coin_id);
+
+        if refunded.is_empty() {
+            self.refunds.remove(funder);
+        } else {
+            self.refunds.insert(funder, &refunded);
+        }
+    }
 
-            // Save the fees that are paid
-            self.fees_paid.insert(&sender_id, &new_fees_paid);
+    pub fn get_withdrawal_window(&self) -> Option<u64> {
+        self.withdrawal_window
+    }
+
+    pub fn set_withdrawal_window(&mut self, withdrawal_window: Option<u64>) {
+        self.withdrawal_window = withdrawal_window;
+    }
 
-            log!("Total for item {} is now at {}", self.identifier, self.progress);
+    // Contributions can be withdrawn until the goal is reached
+    fn is_withdrawal_open(&self) -> bool {
+        matches!(self.status, CrowdfundStatus::Created | CrowdfundStatus::InProgress) && self.progress < self.goal
+    }
 
-            return 0u128;
+    // Whether the funder last funded within the cool-off window, if there is one
+    fn is_within_withdrawal_window(&self, funder: &AccountId) -> bool {
+        match self.withdrawal_window {
+            Some(withdrawal_window) => env::block_timestamp() < self.funded_at.get(funder).unwrap_or(0).saturating_add(withdrawal_window),
+            None => true,
         }
     }
 
-    fn tokenize_item(&mut self) -> Promise {
-        require!(self.progress == self.goal, "Goal not yet reached.");
-        require!(!(self.status == CrowdfundStatus::Tokenized), "This item has already been tokenized."); // TODO only allow tokenization when transporting
+    pub fn can_withdraw(&self, funder: &AccountId) -> bool {
+        self.fundings.get(funder).is_some() && self.is_withdrawal_open() && self.is_within_withdrawal_window(funder)
+    }
 
-        // TOKENIZE: call the custom NFT that creates a token
-        log!("Serializing crowdfund distribution.");
+    // Take back part of a funding (BEFORE! transferring), with the fee paid on it. Returns the netto & fee amounts per coin to transfer,
+    // with the referral credit taken back on them
+    pub fn get_funded_at(&self, funder: &AccountId) -> Option<u64> {
+        self.funded_at.get(funder)
+    }
+
+    pub fn start_withdrawal(&mut self, funder: &AccountId, netto_amount: u128) -> Vec<(AccountId, CoinAmount, Option<ReversedReferral>)> {
+        require!(self.is_withdrawal_open(), "Contributions can only be withdrawn before the goal is reached.");
+        require!(self.is_within_withdrawal_window(funder), "The cool-off window has passed.");
+
+        let withdrawals = self.take_position(funder, netto_amount);
+
+        self.progress -= netto_amount;
+        self.withdrawals_pending += netto_amount;
+        for (coin_id, amount, _) in &withdrawals {
+            let total_coin = self.coin_totals.entry(coin_id.clone()).or_default();
+            total_coin.netto -= amount.netto;
+            total_coin.fee -= amount.fee;
+        }
+
+        withdrawals
+    }
 
-        // Make crowdfund distribution serializable -> split funders (holders) & their funds (shares) into 2 Vec's
-        let holders_serializable = self.fundings.keys_as_vector().to_vec();
-        let shares = self.fundings.values_as_vector().to_vec();
+    // Release the room kept for a withdrawal in a coin once its transfer went through
+    pub fn complete_withdrawal(&mut self, amount: CoinAmount) {
+        self.withdrawals_pending -= amount.netto;
+    }
+
+    // Undo a withdrawal in a coin, e.g. when the transfer failed, including the referral credit taken back with it.
+    // Its room was kept under the hard cap, so restoring it can't surpass the hard cap
+    pub fn rollback_withdrawal(&mut self, funder: &AccountId, coin_id: &AccountId, amount: CoinAmount, referral: Option<ReversedReferral>, funded_at: Option<u64>) {
+        self.add_position(funder, coin_id, amount);
+        if let Some(referral) = referral {
+            self.restore_referral(funder, coin_id, referral);
+        }
+
+        // A funding since the withdrawal is more recent
+        if let Some(funded_at) = funded_at {
+            if self.funded_at.get(funder).is_none() {
+                self.funded_at.insert(funder, &funded_at);
+            }
+        }
+
+        self.withdrawals_pending -= amount.netto;
+        self.progress += amount.netto;
+        let total_coin = self.coin_totals.entry(coin_id.clone()).or_default();
+        total_coin.netto += amount.netto;
+        total_coin.fee += amount.fee;
+    }
+
+    // Positions can change hands once the goal is reached, until the item is tokenized
+    pub fn is_transferable(&self) -> bool {
+        self.progress >= self.goal
+            && matches!(self.status, CrowdfundStatus::InProgress | CrowdfundStatus::Buying | CrowdfundStatus::Transporting)
+            && !self.tokenizing
+    }
+
+    // Move part of the position of a funder to another account, with the fee paid on it. Progress and coin totals stay the same.
+    // Taken from the sender first and given to the receiver after, so the storage each side frees or adds can be measured
+    pub fn start_position_transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, netto_amount: u128) -> Vec<(AccountId, CoinAmount)> {
+        require!(self.is_transferable(), "Positions can only be transferred once the goal is reached, until the item is tokenized.");
+        require!(sender_id != receiver_id, "Can't transfer a position to yourself.");
+        self.assert_receiver_within_limits(sender_id, receiver_id, netto_amount);
+
+        // The receiver isn't credited to the referrer of the sender
+        self.take_position(sender_id, netto_amount)
+            .into_iter()
+            .map(|(coin_id, amount, _)| (coin_id, amount))
+            .collect()
+    }
+
+    // The receiver of a position can't get above the maximum contribution, nor become a funder past the maximum funders.
+    // The transfer adds no progress, so the hard cap and the minimum contribution of fundings don't apply
+    fn assert_receiver_within_limits(&self, sender_id: &AccountId, receiver_id: &AccountId, netto_amount: u128) {
+        let funded_by_receiver = self.fundings.get(receiver_id).unwrap_or(0);
+        let sender_leaves = self.fundings.get(sender_id).unwrap_or(0) == netto_amount;
+        let funder_count = self.fundings.len() - u64::from(sender_leaves);
+
+        match self.limits.room_for(funded_by_receiver, funder_count, netto_amount, netto_amount) {
+            Ok(room) => require!(room >= netto_amount, format!("Transfer would take {} above the maximum contribution.", receiver_id)),
+            Err(reason) => require!(false, reason),
+        }
+    }
+
+    pub fn complete_position_transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, transfers: Vec<(AccountId, CoinAmount)>) {
+        for (coin_id, amount) in &transfers {
+            self.add_position(receiver_id, coin_id, *amount);
+        }
+
+        CrowdfundEvent::CrowdfundPositionTransferred(vec![CrowdfundPositionTransferredData {
+            item_index: self.identifier,
+            old_owner_id: sender_id.clone(),
+            new_owner_id: receiver_id.clone(),
+            netto: U128::from(transfers.iter().map(|(_, amount)| amount.netto).sum::<u128>()),
+            fee: U128::from(transfers.iter().map(|(_, amount)| amount.fee).sum::<u128>()),
+        }]).emit();
+    }
+
+    // Take part of the position of a funder: the netto amount from the coins in order, with the fee paid in proportion
+    // and the referral credit taken back
+    fn take_position(&mut self, funder: &AccountId, netto_amount: u128) -> Vec<(AccountId, CoinAmount, Option<ReversedReferral>)> {
+        let funded = self.fundings.get(funder).unwrap_or(0);
+        require!(netto_amount > 0, "Amount must be more than zero.");
+        require!(netto_amount <= funded, "Amount is more than the contribution.");
 
-        // Make shares serializable
-        let mut shares_serializable: Vec<U128> = Vec::new();
-        for share in &shares {
-            shares_serializable.push(U128::from(*share));
+        let mut funder_coins = self.coin_fundings.get(funder).unwrap_or_default();
+        let mut taken = Vec::new();
+        let mut remaining = netto_amount;
+        for (coin_id, funder_coin) in funder_coins.iter_mut() {
+            let netto = std::cmp::min(remaining, funder_coin.netto);
+            if netto == 0 {
+                continue;
+            }
+
+            let fee = share_of(funder_coin.fee, netto, funder_coin.netto);
+            let referral = self.reverse_referral(funder, coin_id, netto, funder_coin.netto);
+            funder_coin.netto -= netto;
+            funder_coin.fee -= fee;
+            remaining -= netto;
+
+            taken.push((coin_id.clone(), CoinAmount { netto, fee }, referral));
+        }
+        funder_coins.retain(|_, funder_coin| funder_coin.netto > 0 || funder_coin.fee > 0);
+
+        // A funder without a position no longer counts as funder
+        if netto_amount == funded {
+            self.fundings.remove(funder);
+            self.fees_paid.remove(funder);
+            self.coin_fundings.remove(funder);
+            self.funded_at.remove(funder);
+            self.funder_referrals.remove(funder);
+        } else {
+            let fee_amount: u128 = taken.iter().map(|(_, amount, _)| amount.fee).sum();
+            self.fundings.insert(funder, &(funded - netto_amount));
+            self.fees_paid.insert(funder, &(self.fees_paid.get(funder).unwrap_or(0) - fee_amount));
+            self.coin_fundings.insert(funder, &funder_coins);
         }
 
-        // Call NFT mint on nft contract, pass new fungible token info
+        taken
+    }
+
+    // Add to the position of a funder in a coin
+    fn add_position(&mut self, funder: &AccountId, coin_id: &AccountId, amount: CoinAmount) {
+        let funded = self.fundings.get(funder).unwrap_or(0);
+        let fees_paid = self.fees_paid.get(funder).unwrap_or(0);
+        self.fundings.insert(funder, &(funded + amount.netto));
+        self.fees_paid.insert(funder, &(fees_paid + amount.fee));
+
+        let mut funder_coins = self.coin_fundings.get(funder).unwrap_or_default();
+        let funder_coin = funder_coins.entry(coin_id.clone()).or_default();
+        funder_coin.netto += amount.netto;
+        funder_coin.fee += amount.fee;
+        self.coin_fundings.insert(funder, &funder_coins);
+    }
+
+    // The fee part of the refund of a funder in a coin
+    fn get_refunded_fee(&self, funder: &AccountId, coin_id: &AccountId) -> u128 {
+        if !self.refunds_include_fee() {
+            return 0;
+        }
+
+        self.coin_fundings.get(funder).unwrap_or_default().get(coin_id).map(|amount| amount.fee).unwrap_or(0)
+    }
+
+    pub fn get_fees_collected(&self) -> u128 {
+        self.coin_totals.values().map(|amount| amount.fee).sum()
+    }
+
+    pub fn get_fees_withdrawn(&self) -> u128 {
+        self.fees_withdrawn.values().sum()
+    }
+
+    // The fees that are (or may still be) given back to funders, as the item is not tokenized
+    pub fn get_fees_refundable(&self) -> u128 {
+        match self.status {
+            CrowdfundStatus::Tokenized => 0,
+            CrowdfundStatus::OutOfTime | CrowdfundStatus::FailedBuying | CrowdfundStatus::FailedTransporting if !self.refunds_include_fee() => 0,
+            _ => self.get_fees_collected() - self.fees_refunded
+        }
+    }
+
+    // The fees belong to the platform once the item is tokenized, or when it failed and the fees are not given back
+    pub fn is_fee_withdrawable(&self) -> bool {
+        self.status == CrowdfundStatus::Tokenized || (self.is_refundable() && !self.refunds_include_fee())
+    }
+
+    // Register the withdrawal of the fees to the treasury (BEFORE! transferring). Returns the fees per coin to withdraw.
+    // The referral rewards of a tokenized item stay in the contract for the referrers
+    pub fn start_fee_withdrawal(&mut self) -> Vec<(AccountId, u128)> {
+        require!(self.is_fee_withdrawable(), "Fees can only be withdrawn from tokenized items, or failed items that keep their fees.");
+
+        let mut withdrawals: Vec<(AccountId, u128)> = Vec::new();
+        for (coin_id, amount) in &self.coin_totals {
+            let referral_rewards = if self.status == CrowdfundStatus::Tokenized { self.referral_totals.get(coin_id).copied().unwrap_or(0) } else { 0 };
+            let treasury_fee = amount.fee - referral_rewards;
+            let withdrawn = self.fees_withdrawn.get(coin_id).copied().unwrap_or(0);
+            if treasury_fee > withdrawn {
+                withdrawals.push((coin_id.clone(), treasury_fee - withdrawn));
+            }
+        }
+
+        require!(!withdrawals.is_empty(), "No fees to withdraw.");
+
+        for (coin_id, fee_amount) in &withdrawals {
+            *self.fees_withdrawn.entry(coin_id.clone()).or_default() += fee_amount;
+        }
+
+        withdrawals
+    }
+
+    // Undo the registration of a fee withdrawal in a coin, e.g. when the transfer failed
+    pub fn rollback_fee_withdrawal(&mut self, coin_id: &AccountId, fee_amount: u128) {
+        *self.fees_withdrawn.entry(coin_id.clone()).or_default() -= fee_amount;
+    }
+
+    pub fn get_failed_tokenizations(&self) -> u8 {
+        self.failed_tokenizations
+    }
+
+    pub fn get_tokenization_error(&self) -> Option<String> {
+        self.tokenization_error.clone()
+    }
+
+    pub fn get_token_id(&self) -> Option<TokenId> {
+        self.token_id.clone()
+    }
+
+    pub fn get_funder_count(&self) -> u64 {
+        self.fundings.len()
+    }
+
+    pub fn get_funders(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        self.fundings.keys_as_vector()
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // The netto amount funded and the fees paid by a funder
+    pub fn get_contribution(&self, funder: &AccountId) -> (Balance, Balance) {
+        (self.fundings.get(funder).unwrap_or(0), self.fees_paid.get(funder).unwrap_or(0))
+    }
+
+    // Whether nft_mint was called and its callback didn't run yet
+    pub fn is_tokenizing(&self) -> bool {
+        self.tokenizing
+    }
+
+    // Record the result of a tokenization (the minted token id), failed ones can be retried
+    pub fn finish_tokenization(&mut self, result: Result<TokenId, String>) {
+        self.tokenizing = false;
+
+        match result {
+            Err(reason) => {
+                self.failed_tokenizations += 1;
+                log!("Tokenization {} of item {} failed: {}", self.failed_tokenizations, self.identifier, reason);
+                self.tokenization_error = Some(reason);
+            },
+            Ok(token_id) => {
+                self.tokenization_error = None;
+                self.token_id = Some(token_id.clone());
+                self.transition(CrowdfundStatus::Tokenized);
+
+                CrowdfundEvent::CrowdfundTokenized(vec![CrowdfundTokenizedData {
+                    item_index: self.identifier,
+                    token_id,
+                }]).emit();
+            }
+        }
+    }
+
+    // Tokenize the item once it's in the warehouse
+    pub fn tokenize_item(&mut self) -> Promise {
+        require!(self.progress >= self.goal, "Goal not yet reached.");
+        require!(self.status == CrowdfundStatus::Transporting, "Item is not being transported.");
+        require!(self.in_warehouse, "Item is not yet in the warehouse.");
+        require!(!self.tokenizing, "Item is already being tokenized.");
+
+        self.tokenizing = true;
+
+        // TOKENIZE: call the custom NFT that creates a token
+        // Funders claim their share of the token themselves, the FT looks up their funding through get_position
         log!("Calling nft_mint from crowdfund.");
 
         ext_nft::ext(self.nft_account_id.clone())
             .with_static_gas(Gas(10*TGAS))
-            .nft_mint(self.metadata.clone(), U128::from(DEFAULT_TOKEN_SUPPLY), holders_serializable, shares_serializable)
+            .nft_mint(self.metadata.clone(), U128::from(DEFAULT_TOKEN_SUPPLY), self.identifier, U128::from(self.progress))
             .then(
                 ext_crowdfunds::ext(env::current_account_id())    // Callback to this crowdfund contract
                     .with_static_gas(Gas(1*TGAS))
@@ -200,30 +1182,13 @@ impl Crowdfund {
                 )
     }
 
+    // The fee is rounded down, so netto + fee is the amount
     fn split_netto_and_fee(&self, amount: u128) -> (u128, u128) {
-        let item_fee_percentage_dec: Decimal = Decimal::from_f64(self.item_fee_percentage).unwrap();
-        let amount_dec: Decimal = amount.into();
-
-        let fees_dec = amount_dec * (item_fee_percentage_dec / Decimal::from(100));
-        let fee_amount = fees_dec.to_u128().unwrap();
-        let netto_amount: u128 = amount - fee_amount;
-
-        (netto_amount, fee_amount)
+        split_fee(amount, self.item_fee_bps)
     }
 
-    fn calculate_leftovers(&self, netto_amount: u128, fee_amount: u128) -> (u128, u128) {
-        let netto_leftover = self.progress + netto_amount - self.goal;
-
-        let netto_leftover_dec: Decimal = netto_leftover.into();
-        let netto_amount_dec: Decimal = netto_amount.into();
-
-        let percentage_returned_dec: Decimal = netto_leftover_dec / netto_amount_dec;
-
-        let fee_amount_dec: Decimal = fee_amount.into();
-        let fee_leftover_dec: Decimal = fee_amount_dec * (percentage_returned_dec / Decimal::from(100));
-
-        let fee_leftover: u128 = fee_leftover_dec.to_u128().unwrap();
-
-        (netto_leftover, fee_leftover)
+    // Split what doesn't fit in the room left into netto & fee leftovers, the fee kept is proportional to the netto kept
+    fn calculate_leftovers(&self, netto_amount: u128, fee_amount: u128, room: u128) -> (u128, u128) {
+        split_leftover(netto_amount, fee_amount, room)
     }
 }
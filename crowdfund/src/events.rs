@@ -0,0 +1,165 @@
+//! Module for the events of the crowdfund contract, logged following NEP-297.
+
+use crate::*;
+
+use near_sdk::serde::Serialize;
+
+use near_sdk::json_types::U128;
+
+use pause::Feature;
+
+const EVENT_STANDARD: &str = "wehave_crowdfund";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum CrowdfundEvent {
+    Pause(Vec<PauseData>),
+    Unpause(Vec<PauseData>),
+    CrowdfundCreated(Vec<CrowdfundCreatedData>),
+    CrowdfundFunded(Vec<CrowdfundFundedData>),
+    CrowdfundGoalReached(Vec<CrowdfundGoalReachedData>),
+    CrowdfundHardCapReached(Vec<CrowdfundHardCapReachedData>),
+    CrowdfundStatusChanged(Vec<CrowdfundStatusChangedData>),
+    CrowdfundCancelled(Vec<CrowdfundCancelledData>),
+    CrowdfundRefunded(Vec<CrowdfundRefundedData>),
+    CrowdfundWithdrawn(Vec<CrowdfundWithdrawnData>),
+    CrowdfundPositionTransferred(Vec<CrowdfundPositionTransferredData>),
+    CrowdfundReferralRewardClaimed(Vec<CrowdfundReferralRewardClaimedData>),
+    CrowdfundTokenized(Vec<CrowdfundTokenizedData>)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseData {
+    pub feature: Feature,
+    pub account_id: AccountId
+}
+
+// Amounts are in the internal unit, unless stated otherwise
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundCreatedData {
+    pub item_index: u64,
+    pub goal: U128,
+    pub hard_cap: U128,
+    pub fee_bps: u16,
+    pub deadline: u64,
+
+    // When a scheduled crowdfund opens for funding
+    pub start_timestamp: Option<u64>
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundFundedData {
+    pub item_index: u64,
+    pub account_id: AccountId,
+    pub coin_id: AccountId,
+    pub netto: U128,
+    pub fee: U128,
+    pub progress: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundGoalReachedData {
+    pub item_index: u64,
+    pub goal: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundHardCapReachedData {
+    pub item_index: u64,
+    pub hard_cap: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundStatusChangedData {
+    pub item_index: u64,
+    pub old_status: CrowdfundStatus,
+    pub new_status: CrowdfundStatus
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundCancelledData {
+    pub item_index: u64,
+    pub reason: String
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundRefundedData {
+    pub item_index: u64,
+    pub account_id: AccountId,
+    pub coin_id: AccountId,
+
+    // In units of the coin
+    pub amount: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundWithdrawnData {
+    pub item_index: u64,
+    pub account_id: AccountId,
+    pub coin_id: AccountId,
+
+    // In units of the coin, netto and fee
+    pub amount: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundPositionTransferredData {
+    pub item_index: u64,
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    pub netto: U128,
+    pub fee: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundReferralRewardClaimedData {
+    pub item_index: u64,
+    pub account_id: AccountId,
+    pub coin_id: AccountId,
+
+    // In units of the coin
+    pub amount: U128
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundTokenizedData {
+    pub item_index: u64,
+    pub token_id: TokenId
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'a str,
+    version: &'a str,
+
+    #[serde(flatten)]
+    event: &'a CrowdfundEvent
+}
+
+impl CrowdfundEvent {
+    pub fn emit(&self) {
+        let event_log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+
+        env::log_str(&format!("EVENT_JSON:{}", near_sdk::serde_json::to_string(&event_log).unwrap()));
+    }
+}
@@ -0,0 +1,124 @@
+//! Module for the fee treasury: withdrawing collected fees and accounting views.
+
+use crate::*;
+
+use near_sdk::serde::Serialize;
+
+// The number of crowdfunds summed when no limit is given
+const DEFAULT_PLATFORM_FEES_LIMIT: u64 = 50;
+
+// Fee accounting of a crowdfund or the whole platform, in the internal unit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeesView {
+    // All fees paid by funders
+    pub collected: U128,
+
+    // Fees transferred to the treasury
+    pub withdrawn: U128,
+
+    // Fees that are (or may still be) given back to funders
+    pub refundable: U128,
+
+    // Fees credited to referrers, which are not withdrawn to the treasury
+    pub referral_rewards: U128
+}
+
+impl FeesView {
+    fn from_crowdfund(crowdfund: &Crowdfund) -> Self {
+        Self {
+            collected: U128::from(crowdfund.get_fees_collected()),
+            withdrawn: U128::from(crowdfund.get_fees_withdrawn()),
+            refundable: U128::from(crowdfund.get_fees_refundable()),
+            referral_rewards: U128::from(crowdfund.get_referral_rewards()),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_treasury(&mut self, treasury_account_id: AccountId) {
+        self.assert_role(Role::Owner);
+        self.treasury_account_id = treasury_account_id;
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_account_id.clone()
+    }
+
+    // Transfer the fees of a tokenized crowdfund, or a failed one that keeps its fees, to the treasury in the coins they were paid with
+    pub fn withdraw_fees(&mut self, item_index: u64) -> Promise {
+        self.assert_role(Role::Treasurer);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        let withdrawals = crowdfund.start_fee_withdrawal();
+
+        // Save the withdrawal BEFORE transferring, so fees can't be withdrawn twice
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        let mut withdrawal_promise: Option<Promise> = None;
+        for (coin_id, fee_amount) in withdrawals {
+            let coin_amount = self.to_coin_amount(&coin_id, fee_amount);
+
+            log!("Withdrawing {} {} of fees of item {} to {}", coin_amount, coin_id, item_index, self.treasury_account_id);
+
+            let transfer = ext_ft::ext(coin_id.clone())
+                .with_attached_deposit(1)
+                .with_static_gas(Gas(5*TGAS))
+                .ft_transfer(self.treasury_account_id.clone(), U128::from(coin_amount), Some(format!("Fees of item {}", item_index)))
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5*TGAS))
+                        .withdraw_fees_callback(item_index, coin_id, U128::from(fee_amount))
+                );
+
+            withdrawal_promise = Some(match withdrawal_promise {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+
+        withdrawal_promise.unwrap()
+    }
+
+    #[private]
+    pub fn withdraw_fees_callback(&mut self, item_index: u64, coin_id: AccountId, fee_amount: U128, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            log!("Withdrawing fees of item {} in {} failed. They can be withdrawn again.", item_index, coin_id);
+
+            let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+            crowdfund.rollback_fee_withdrawal(&coin_id, fee_amount.into());
+            self.crowdfunds.replace(item_index, &crowdfund);
+        }
+    }
+
+    pub fn get_crowdfund_fees(&self, item_index: u64) -> FeesView {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+        FeesView::from_crowdfund(&crowdfund)
+    }
+
+    // The fees of the crowdfunds starting at from_index together. The platform total is the sum over all pages
+    pub fn get_platform_fees(&self, from_index: Option<u64>, limit: Option<u64>) -> FeesView {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_PLATFORM_FEES_LIMIT);
+        require!(limit > 0, "Limit must be greater than 0.");
+
+        let (mut collected, mut withdrawn, mut refundable, mut referral_rewards) = (0u128, 0u128, 0u128, 0u128);
+
+        for item_index in from_index..std::cmp::min(from_index.saturating_add(limit), self.crowdfunds.len()) {
+            let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+
+            collected += crowdfund.get_fees_collected();
+            withdrawn += crowdfund.get_fees_withdrawn();
+            refundable += crowdfund.get_fees_refundable();
+            referral_rewards += crowdfund.get_referral_rewards();
+        }
+
+        FeesView {
+            collected: U128::from(collected),
+            withdrawn: U128::from(withdrawn),
+            refundable: U128::from(refundable),
+            referral_rewards: U128::from(referral_rewards),
+        }
+    }
+}
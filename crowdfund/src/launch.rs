@@ -0,0 +1,81 @@
+//! Module for scheduled launches: a crowdfund can be created before it opens for funding.
+//!
+//! A scheduled crowdfund stays Created until its start timestamp, visible but not fundable. It is
+//! reported as started by views from then on, and saved as started by the first change after the start, or earlier by an operator.
+//! Accounts an operator gave early access to can fund a scheduled crowdfund from its early access timestamp on. Early access
+//! is kept per crowdfund, apart from the compliance allowlist.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+// When a created crowdfund opens for funding, timestamps in nanoseconds
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LaunchSchedule {
+    // Everyone allowed by the access of the crowdfund can fund from this timestamp on
+    pub start_timestamp: u64,
+
+    // Accounts with early access can fund from this timestamp on, before the start
+    pub early_access_timestamp: Option<u64>
+}
+
+impl LaunchSchedule {
+    pub fn assert_valid(&self, deadline: u64) {
+        require!(self.start_timestamp > env::block_timestamp(), "Start timestamp is in the past.");
+        require!(self.start_timestamp < deadline, "Start timestamp is after the end timestamp.");
+
+        if let Some(early_access_timestamp) = self.early_access_timestamp {
+            require!(early_access_timestamp < self.start_timestamp, "Early access must open before the start.");
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        env::block_timestamp() >= self.start_timestamp
+    }
+
+    pub fn is_early_access_open(&self) -> bool {
+        self.early_access_timestamp.is_some_and(|early_access_timestamp| env::block_timestamp() >= early_access_timestamp)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Change when a created crowdfund opens for funding
+    pub fn set_launch_schedule(&mut self, item_index: u64, launch: LaunchSchedule) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        launch.assert_valid(crowdfund.get_deadline());
+        crowdfund.set_launch(launch);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_launch_schedule(&self, item_index: u64) -> Option<LaunchSchedule> {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_launch()
+    }
+
+    pub fn add_early_access(&mut self, item_index: u64, account_ids: Vec<AccountId>) {
+        self.set_early_access(item_index, account_ids, true);
+    }
+
+    pub fn remove_early_access(&mut self, item_index: u64, account_ids: Vec<AccountId>) {
+        self.set_early_access(item_index, account_ids, false);
+    }
+
+    pub fn has_early_access(&self, item_index: u64, account_id: AccountId) -> bool {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").has_early_access(&account_id)
+    }
+}
+
+impl Contract {
+    fn set_early_access(&mut self, item_index: u64, account_ids: Vec<AccountId>, early_access: bool) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        for account_id in account_ids.iter() {
+            crowdfund.set_early_access(account_id, early_access);
+        }
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+}
@@ -1,18 +1,54 @@
+mod access;
+mod coins;
+mod contributions;
 mod crowdfund;
+mod events;
+mod fees;
+mod launch;
+mod limits;
+mod message;
+mod migration;
+mod options;
+mod pause;
+mod positions;
+mod referrals;
+mod refund;
+mod roles;
+mod state;
+mod storage;
+mod views;
+mod withdrawals;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{log, near_bindgen, ext_contract, require, env, AccountId, BorshStorageKey, Balance, CryptoHash, PanicOnDefault, Promise, Gas, PromiseError, PromiseOrValue};
-use near_sdk::collections::{UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{assert_one_yocto, log, near_bindgen, ext_contract, require, env, AccountId, BorshStorageKey, Balance, CryptoHash, PanicOnDefault, Promise, Gas, PromiseError, PromiseOrValue};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::U128;
 use near_sdk::serde_json::json;
 
 use near_contract_standards::non_fungible_token::metadata::{TokenMetadata};
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 
+use std::collections::BTreeSet;
+
+use access::Access;
+use coins::{Coin, CoinTotalView};
 use crowdfund::Crowdfund;
 use crowdfund::CrowdfundStatus;
+use crowdfund::TGAS;
+use crowdfund::BPS_DENOMINATOR;
+use events::{CrowdfundEvent, CrowdfundCreatedData};
+use launch::LaunchSchedule;
+use limits::ContributionLimits;
+use message::FundingMessage;
+use options::NewItemOptions;
+use pause::Feature;
+use roles::Role;
+use state::CrowdfundVector;
+use storage::StorageAccount;
 
 const DEFAULT_TOKEN_DECIMALS: u8 = 6;
+const DEFAULT_MAX_TOKENIZATION_ATTEMPTS: u8 = 3;
+const DEFAULT_FEE_BPS: u16 = 400;
 
 // Define the state of the smart contract
 #[near_bindgen]
@@ -24,32 +60,72 @@ pub struct Contract {
     // The number of decimals for interpreting the balance amounts
     decimals: u8,
 
-    // The stablecoin accepted as payment for crowdfunds
-    accepted_coin: AccountId,
+    // The stablecoins accepted as payment for crowdfunds (coin -> decimals & whether accepted)
+    coins: UnorderedMap<AccountId, Coin>,
 
     // The account id of the items collection used for tokenization
     nft_account_id: AccountId,
 
-    // The default fee % taken on crowdfunds
-    default_fee_percentage: f64,
+    // The default fee taken on crowdfunds, in basis points (1/100th of a %)
+    default_fee_bps: u16,
+
+    // The account collected fees are withdrawn to
+    treasury_account_id: AccountId,
 
     // The list of crowdfunds
-    crowdfunds: Vector<Crowdfund>,
+    crowdfunds: CrowdfundVector,
+
+    // The account that configures the contract and grants roles
+    owner_id: AccountId,
+
+    // The account proposed as new owner, until it accepts
+    pending_owner_id: Option<AccountId>,
+
+    // The accounts that were granted a role (role -> accounts)
+    roles: LookupMap<Role, UnorderedSet<AccountId>>,
+
+    // The number of failed tokenizations after which a crowdfund may be abandoned
+    max_tokenization_attempts: u8,
+
+    // The crowdfunds each account contributed to (account -> item indices)
+    contributions: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    // The parts of the contract that are paused
+    paused: BTreeSet<Feature>,
 
-    // List of accounts allowed to create or delete a crowdfund
-    crowdfund_operators: Vector<AccountId>
+    // The accounts verified by the compliance role, for crowdfunds restricted to the allowlist
+    allowlist: UnorderedSet<AccountId>,
+
+    // The storage deposits of accounts, which pay for the items and fundings they add (NEP-145)
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+
+    // The storage taken by registering an account, covered by the minimum storage balance
+    account_storage_usage: u64
 }
 
 // Define storage keys for collections and nested collections
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Crowdfunds,
-    CrowdfundOperators
+    CrowdfundOperators,     // No longer used: operators are kept in roles
+    Coins,
+    Contributions,
+    AccountContributions { account_hash: CryptoHash },
+    Roles,
+    RoleMembers { role: Role },
+    Allowlist,
+    StorageAccounts
 }
 
 #[ext_contract(ext_nft)]
 trait NonFungibleToken {
-    fn nft_mint(&mut self, token_metadata: TokenMetadata, ft_supply: U128, holders: Vec<AccountId>, shares: Vec<U128>);
+    fn nft_mint(&mut self, token_metadata: TokenMetadata, ft_supply: U128, item_index: u64, total_funding: U128);
+}
+
+#[ext_contract(ext_ft)]
+#[allow(dead_code)]
+trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
 
 pub trait FungibleTokenReceiver {
@@ -59,50 +135,145 @@ pub trait FungibleTokenReceiver {
 #[near_bindgen]
 impl Contract {
     #[init]
-    pub fn new(nft_account_id: AccountId, accepted_coin: AccountId) -> Self {
+    pub fn new(nft_account_id: AccountId, accepted_coin: AccountId, accepted_coin_decimals: u8, owner_id: Option<AccountId>) -> Self {
         require!(!env::state_exists(), "Already initialized");
 
+        let mut coins = UnorderedMap::new(StorageKeys::Coins);
+        coins.insert(&accepted_coin, &Coin { decimals: accepted_coin_decimals, accepted: true });
+
+        state::write_state_version();
+
         Self{
             base_uri: String::from("test"),
             decimals: DEFAULT_TOKEN_DECIMALS,
-            accepted_coin: accepted_coin,
+            coins,
             nft_account_id: nft_account_id,
-            default_fee_percentage: 4.0,
-            crowdfunds: Vector::new(StorageKeys::Crowdfunds),
-            crowdfund_operators: Vector::new(StorageKeys::CrowdfundOperators),
+            default_fee_bps: DEFAULT_FEE_BPS,
+            treasury_account_id: env::current_account_id(),
+            crowdfunds: CrowdfundVector::new(StorageKeys::Crowdfunds),
+            owner_id: owner_id.unwrap_or_else(env::current_account_id),
+            pending_owner_id: None,
+            roles: LookupMap::new(StorageKeys::Roles),
+            max_tokenization_attempts: DEFAULT_MAX_TOKENIZATION_ATTEMPTS,
+            contributions: LookupMap::new(StorageKeys::Contributions),
+            paused: BTreeSet::new(),
+            allowlist: UnorderedSet::new(StorageKeys::Allowlist),
+            storage_accounts: LookupMap::new(StorageKeys::StorageAccounts),
+            account_storage_usage: storage::measure_account_storage_usage(),
         }
     }
 
-    pub fn new_item(&mut self, item_metadata: TokenMetadata, goal: u128) {
-        require!(self.caller_is_operator(), "Caller is not allowed to create a crowdfund.");
+    // Create a crowdfund, the operator pays for its storage. With a launch schedule, it opens for funding at the start timestamp
+    pub fn new_item(&mut self, item_metadata: TokenMetadata, goal: u128, end_timestamp: u64, options: Option<NewItemOptions>) {
+        self.assert_role(Role::Operator);
+        self.assert_not_paused(Feature::ItemCreation);
         require!(goal > 0, "Goal is smaller than zero.");
+        require!(end_timestamp > env::block_timestamp(), "End timestamp is in the past.");
+
+        let options = options.unwrap_or_default();
+        let item_fee_bps = options.fee_bps.unwrap_or(self.default_fee_bps);
+        require!(u128::from(item_fee_bps) <= BPS_DENOMINATOR, "Fee can't be more than 10000 bps.");
+
+        if let Some(limits) = &options.limits {
+            limits.assert_valid();
+        }
+
+        if let Some(launch) = &options.launch {
+            launch.assert_valid(end_timestamp);
+        }
 
         let amt = u64::from(self.crowdfunds.len());
-        let new_crowdfund = Crowdfund::new(self.nft_account_id.clone(), amt, item_metadata, goal, self.default_fee_percentage);
+        let new_crowdfund = Crowdfund::new(self.nft_account_id.clone(), amt, item_metadata, goal, item_fee_bps, end_timestamp, options);
 
+        let initial_storage = env::storage_usage();
         self.crowdfunds.push(&new_crowdfund);
+        self.charge_storage(&env::predecessor_account_id(), initial_storage);
+
+        CrowdfundEvent::CrowdfundCreated(vec![CrowdfundCreatedData {
+            item_index: amt,
+            goal: U128::from(goal),
+            hard_cap: U128::from(new_crowdfund.get_hard_cap()),
+            fee_bps: item_fee_bps,
+            deadline: end_timestamp,
+            start_timestamp: new_crowdfund.get_launch().map(|launch| launch.start_timestamp),
+        }]).emit();
     }
 
-    pub fn add_operator(&mut self, operator: AccountId) {
-        require!(env::predecessor_account_id() == env::current_account_id(), "Only this contract itself can add an operator.");
-        self.crowdfund_operators.push(&operator);
+    // Change the fee of crowdfunds created from now on
+    pub fn set_default_fee_bps(&mut self, fee_bps: u16) {
+        self.assert_role(Role::Owner);
+        require!(u128::from(fee_bps) <= BPS_DENOMINATOR, "Fee can't be more than 10000 bps.");
+        self.default_fee_bps = fee_bps;
     }
 
-    fn caller_is_operator(&self) -> bool {
-        for operator in self.crowdfund_operators.iter() {
-            if env::predecessor_account_id() == operator {
-                return true;
-            }
+    pub fn get_default_fee_bps(&self) -> u16 {
+        self.default_fee_bps
+    }
+
+    pub fn set_max_tokenization_attempts(&mut self, max_tokenization_attempts: u8) {
+        self.assert_role(Role::Owner);
+        require!(max_tokenization_attempts > 0, "At least one tokenization attempt is needed.");
+        self.max_tokenization_attempts = max_tokenization_attempts;
+    }
+
+    // Accept a stablecoin as payment. A coin that was accepted before keeps its decimals
+    pub fn add_accepted_coin(&mut self, coin_id: AccountId, decimals: u8) {
+        self.assert_role(Role::Owner);
+
+        if let Some(coin) = self.coins.get(&coin_id) {
+            require!(coin.decimals == decimals, "Coin is already known with other decimals.");
         }
 
-        return false;
+        self.coins.insert(&coin_id, &Coin { decimals, accepted: true });
+    }
+
+    // Stop accepting new fundings in a stablecoin. Existing fundings can still be refunded in it
+    pub fn remove_accepted_coin(&mut self, coin_id: AccountId) {
+        self.assert_role(Role::Owner);
+
+        let mut coin = self.coins.get(&coin_id).expect("Unknown coin!");
+        coin.accepted = false;
+        self.coins.insert(&coin_id, &coin);
+    }
+
+    // The accepted stablecoins with their decimals
+    pub fn get_accepted_coins(&self) -> Vec<(AccountId, u8)> {
+        self.coins.iter()
+            .filter(|(_, coin)| coin.accepted)
+            .map(|(coin_id, coin)| (coin_id, coin.decimals))
+            .collect()
+    }
+
+    // The netto & fee totals funded per coin on a crowdfund, in units of each coin
+    pub fn get_crowdfund_coin_totals(&self, item_index: u64) -> Vec<CoinTotalView> {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+
+        crowdfund.get_coin_totals()
+            .into_iter()
+            .map(|(coin_id, amount)| CoinTotalView {
+                netto: U128::from(self.to_coin_amount(&coin_id, amount.netto)),
+                fee: U128::from(self.to_coin_amount(&coin_id, amount.fee)),
+                coin_id,
+            })
+            .collect()
+    }
+
+    // Convert an amount in the internal unit to an amount of the coin
+    fn to_coin_amount(&self, coin_id: &AccountId, amount: Balance) -> Balance {
+        let coin = self.coins.get(coin_id).expect("Unknown coin!");
+        coins::denormalize(amount, coin.decimals, self.decimals)
+    }
+
+    // Refuse a funding by returning the full amount as unused
+    fn reject_funding(&self, reason: String, amount: U128) -> PromiseOrValue<U128> {
+        log!("Funding rejected: {}", reason);
+        PromiseOrValue::Value(amount)
     }
 
     pub fn get_current_items(&self) -> Vec<TokenMetadata> {
         let mut metadata_list = vec!();
-        let crowdfund_vec = self.crowdfunds.to_vec();
 
-        for crowdfund in &crowdfund_vec {
+        for crowdfund in self.crowdfunds.iter() {
             metadata_list.push(crowdfund.get_metadata());
         }
 
@@ -113,45 +284,216 @@ impl Contract {
         self.crowdfunds.get(item_index).expect("Incorrect item index!").get_progress()
     }
 
-    pub fn get_crowdfund_fee_percentage(&self, item_index: u64) -> f64 {
-        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_fee_percentage()
+    pub fn get_crowdfund_fee_bps(&self, item_index: u64) -> u16 {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_fee_bps()
     }
 
     pub fn get_crowdfund_goal(&self, item_index: u64) -> u128 {
         self.crowdfunds.get(item_index).expect("Incorrect item index!").get_goal()
     }
 
+    pub fn get_crowdfund_deadline(&self, item_index: u64) -> u64 {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_deadline()
+    }
+
+    pub fn get_crowdfund_status(&self, item_index: u64) -> CrowdfundStatus {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").effective_status()
+    }
+
+    // Open a created crowdfund for funding
+    pub fn start_crowdfund(&mut self, item_index: u64) {
+        self.operator_transition(item_index, CrowdfundStatus::InProgress);
+    }
+
+    // Start buying the item once the goal is reached and funding closed at the hard cap or the deadline
+    pub fn start_buying(&mut self, item_index: u64) {
+        self.operator_transition(item_index, CrowdfundStatus::Buying);
+    }
+
+    // The item was bought and is on its way to the warehouse
+    pub fn start_transporting(&mut self, item_index: u64) {
+        self.operator_transition(item_index, CrowdfundStatus::Transporting);
+    }
+
+    pub fn fail_buying(&mut self, item_index: u64) {
+        self.operator_transition(item_index, CrowdfundStatus::FailedBuying);
+    }
+
+    pub fn fail_transporting(&mut self, item_index: u64) {
+        self.operator_transition(item_index, CrowdfundStatus::FailedTransporting);
+    }
+
+    // The item arrived in the warehouse and can be tokenized
+    pub fn confirm_in_warehouse(&mut self, item_index: u64) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.set_in_warehouse();
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    // Tokenize the item by minting it in the items collection
+    pub fn tokenize(&mut self, item_index: u64) -> Promise {
+        self.assert_role(Role::Operator);
+        self.assert_not_paused(Feature::Tokenization);
+
+        log!("Initiating tokenization...");
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        let promise = crowdfund.tokenize_item();
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        promise
+    }
+
+    // Tokenize the item again after nft_mint failed
+    pub fn retry_tokenization(&mut self, item_index: u64) -> Promise {
+        self.assert_role(Role::Operator);
+        self.assert_not_paused(Feature::Tokenization);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        require!(crowdfund.get_failed_tokenizations() > 0, "Tokenization of this item has not failed.");
+
+        log!("Retrying tokenization of item {} after {} failed attempts...", item_index, crowdfund.get_failed_tokenizations());
+
+        let promise = crowdfund.tokenize_item();
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        promise
+    }
+
+    // Give up tokenizing after too many failed attempts, so funders can claim a refund
+    pub fn abandon_tokenization(&mut self, item_index: u64) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        require!(crowdfund.get_failed_tokenizations() >= self.max_tokenization_attempts, "Tokenization can't be abandoned yet.");
+        require!(!crowdfund.is_tokenizing(), "Item is being tokenized.");
+
+        crowdfund.transition(CrowdfundStatus::FailedTransporting);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_failed_tokenizations(&self, item_index: u64) -> u8 {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_failed_tokenizations()
+    }
+
+    pub fn get_tokenization_error(&self, item_index: u64) -> Option<String> {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_tokenization_error()
+    }
+
+    fn operator_transition(&mut self, item_index: u64, next: CrowdfundStatus) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.check_deadline();
+        crowdfund.transition(next);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
     #[handle_result]
     #[private]
-    #[payable]
-    pub fn nft_mint_callback(&mut self, item_index: u64, #[callback_result] call_result: Result<(), PromiseError>) {
-        if call_result.is_err() {
-            log!("Something went wrong during nft_mint.");
-            // Decide what to do here
-            // Potentially give back fundings
-            // Or try again
-        } else {
-            log!("nft_mint was successful!");
-            self.crowdfunds.get(item_index).unwrap().set_status(CrowdfundStatus::Tokenized);
+    pub fn nft_mint_callback(&mut self, item_index: u64, #[callback_result] call_result: Result<TokenId, PromiseError>) {
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+
+        match call_result {
+            Err(error) => {
+                log!("Something went wrong during nft_mint.");
+                // Keep the item in Transporting: an operator can retry or abandon the tokenization
+                crowdfund.finish_tokenization(Err(format!("nft_mint failed: {:?}", error)));
+            },
+            Ok(token_id) => {
+                log!("nft_mint was successful, minted token {}!", token_id);
+                crowdfund.finish_tokenization(Ok(token_id));
+            }
         }
+
+        self.crowdfunds.replace(item_index, &crowdfund);
     }
 }
 
 #[near_bindgen]
 impl FungibleTokenReceiver for Contract {
     fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
-        require!(env::predecessor_account_id() == self.accepted_coin, "This coin is not accepted as payment.");
-        // TODO return the coin?
+        self.process_funding(sender_id, env::predecessor_account_id(), amount, msg, false)
+    }
+}
+
+impl Contract {
+    // Fund a crowdfund with the coins received. Verified is set once the registry approved the beneficiary
+    pub(crate) fn process_funding(&mut self, sender_id: AccountId, coin_id: AccountId, amount: U128, msg: String, verified: bool) -> PromiseOrValue<U128> {
+        if self.is_paused(Feature::Funding) {
+            return self.reject_funding(String::from("Funding is paused."), amount);
+        }
 
-        // Check if the crowdfund is ilegible for funding TODO use status
-        let item_index: u64 = msg.parse().unwrap();
+        let coin = match self.coins.get(&coin_id).filter(|coin| coin.accepted) {
+            Some(coin) => coin,
+            None => return self.reject_funding(String::from("This coin is not accepted as payment."), amount),
+        };
 
-        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
-        let leftover = crowdfund.fund(sender_id, u128::from(amount));
+        // Funding is accounted in the internal unit, dust that can't be represented is returned
+        let (normalized_amount, dust) = coins::normalize(u128::from(amount), coin.decimals, self.decimals);
+        if normalized_amount == 0 {
+            return self.reject_funding(String::from("Amount is too small."), amount);
+        }
+
+        let message = match FundingMessage::parse(&msg) {
+            Ok(message) => message,
+            Err(reason) => return self.reject_funding(reason, amount),
+        };
+
+        let item_index = message.item_index;
+        let mut crowdfund = match self.crowdfunds.get(item_index) {
+            Some(crowdfund) => crowdfund,
+            None => return self.reject_funding(format!("Item {} does not exist.", item_index), amount),
+        };
+
+        // Accounts with early access can fund a scheduled crowdfund before it starts
+        let beneficiary = message.beneficiary.unwrap_or(sender_id.clone());
+        let early_access = crowdfund.has_early_access(&beneficiary);
+
+        // A crowdfund that ran out of time is saved as such, the other rejections leave it untouched
+        if !crowdfund.is_fundable(early_access) {
+            crowdfund.check_deadline();
+            self.crowdfunds.replace(item_index, &crowdfund);
+            return self.reject_funding(format!("Item {} can't be funded.", item_index), amount);
+        }
+
+        if let Some(max_fee_bps) = message.max_fee_bps {
+            if crowdfund.get_fee_bps() > max_fee_bps {
+                return self.reject_funding(format!("Fee of item {} is higher than {} bps.", item_index, max_fee_bps), amount);
+            }
+        }
+
+        if message.fill_exactly && (dust > 0 || crowdfund.would_overfund(&beneficiary, normalized_amount)) {
+            return self.reject_funding(format!("Amount can't be used exactly for item {}.", item_index), amount);
+        }
+
+        // The beneficiary pays for the storage of its funding
+        if !self.is_storage_registered(&beneficiary) {
+            return self.reject_funding(format!("Account {} is not registered for storage.", beneficiary), amount);
+        }
+
+        match crowdfund.get_access() {
+            Access::Allowlist if !self.allowlist.contains(&beneficiary) => {
+                return self.reject_funding(format!("Account {} is not on the allowlist.", beneficiary), amount);
+            },
+            Access::Registry { registry_account_id } if !verified => {
+                return self.verify_and_fund(registry_account_id, sender_id, coin_id, amount, msg, beneficiary);
+            },
+            _ => {}
+        }
+
+        let initial_storage = env::storage_usage();
+        let leftover = crowdfund.fund(beneficiary.clone(), coin_id.clone(), normalized_amount, message.referral, early_access);
 
         self.crowdfunds.replace(item_index, &crowdfund);
+        if leftover < normalized_amount {
+            self.record_contribution(&beneficiary, item_index);
+        }
+        self.charge_storage(&beneficiary, initial_storage);
 
-        PromiseOrValue::Value(U128::from(leftover))
+        PromiseOrValue::Value(U128::from(self.to_coin_amount(&coin_id, leftover) + dust))
     }
 }
 
@@ -161,11 +503,15 @@ impl FungibleTokenReceiver for Contract {
  */
 #[cfg(test)]
 mod tests {
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, Balance};
+    use near_sdk::test_utils::{self, accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::ONE_NEAR;
+    use near_contract_standards::storage_management::StorageManagement;
 
     use super::*;
-
+    use migration::MintedItem;
+    use referrals::ReversedReferral;
+    use withdrawals::CoinWithdrawal;
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -176,23 +522,1440 @@ mod tests {
         builder
     }
 
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    const STORAGE_DEPOSIT: Balance = ONE_NEAR;
+
+    fn funding_msg(item_index: u64) -> String {
+        json!({"item_index": item_index}).to_string()
+    }
+
+    fn unused_amount(result: PromiseOrValue<U128>) -> u128 {
+        match result {
+            PromiseOrValue::Value(unused) => unused.0,
+            PromiseOrValue::Promise(_) => panic!("Expected a value"),
+        }
+    }
+
+    // The names of the NEP-297 events logged so far
+    fn event_names() -> Vec<String> {
+        test_utils::get_logs()
+            .iter()
+            .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+            .map(|event| near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(event).unwrap()["event"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn sample_token_metadata() -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Ferrari F40".into()),
+            description: Some("Last ferrari built under the supervision of Enzo Ferrari himself.".into()),
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some("ferrarif40".into()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    // Deposit the storage of the test accounts, which covers everything they fund or create
+    fn register_storage(contract: &mut Contract, context: &mut VMContextBuilder) {
+        for index in 0..6 {
+            testing_env!(context.attached_deposit(STORAGE_DEPOSIT).build());
+            contract.storage_deposit(Some(accounts(index)), None);
+        }
+        testing_env!(context.attached_deposit(0).build());
+    }
+
+    // Contract with accounts(0) as operator and a single crowdfund with a goal of 1000, ending after one day
+    fn setup_contract(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new("nft.test.near".parse().unwrap(), accounts(4), DEFAULT_TOKEN_DECIMALS, None);
+        register_storage(&mut contract, context);
+        contract.grant_role(Role::Operator, accounts(0));
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+        contract
+    }
+
     #[test]
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new("test.near".parse().unwrap());
+        let contract = Contract::new("nft.test.near".parse().unwrap(), accounts(4), DEFAULT_TOKEN_DECIMALS, None);
         testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_current_items().len(), 0);
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Operator, accounts(2));
+        contract.grant_role(Role::Pauser, accounts(2));
+        assert_eq!(contract.get_role_members(Role::Operator), vec![accounts(0), accounts(2)]);
+        assert_eq!(contract.get_role_members(Role::Owner), vec![accounts(0)]);
+
+        contract.revoke_role(Role::Operator, accounts(2));
+        assert!(!contract.has_role(Role::Operator, accounts(2)));
+        assert!(contract.has_role(Role::Pauser, accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Caller does not have the Operator role.")]
+    fn test_new_item_without_role() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+    }
+
+    #[test]
+    fn test_transfer_ownership() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.transfer_ownership(accounts(1));
+        assert_eq!(contract.get_owner(), accounts(0));
+        assert_eq!(contract.get_pending_owner(), Some(accounts(1)));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner(), accounts(1));
+        assert_eq!(contract.get_pending_owner(), None);
+
+        contract.set_default_fee_bps(100);
+        assert_eq!(contract.get_default_fee_bps(), 100);
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Pauser, accounts(2));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.pause(Feature::Funding);
+        contract.pause(Feature::Tokenization);
+        assert_eq!(contract.get_paused_features(), vec![Feature::Funding, Feature::Tokenization]);
+        assert!(test_utils::get_logs()[0].starts_with("EVENT_JSON:{\"standard\":\"wehave_crowdfund\",\"version\":\"1.0.0\",\"event\":\"pause\""));
+
+        // Paused fundings are returned in full
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0))), 500);
+        assert_eq!(contract.get_crowdfund_progress(0), 0);
+
+        // Item creation was not paused
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.unpause(Feature::Funding);
+        assert!(!contract.is_paused(Feature::Funding));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0))), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ItemCreation is paused.")]
+    fn test_new_item_paused() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Pauser, accounts(0));
+        contract.pause(Feature::ItemCreation);
+
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
     }
 
     #[test]
     fn test_new_item() {
         let mut context = get_context(accounts(1));
+        let contract = setup_contract(&mut context);
+        assert_eq!(contract.get_current_items(), vec!(sample_token_metadata()));
+        assert_eq!(contract.get_crowdfund_goal(0), 1000);
+        assert_eq!(contract.get_crowdfund_deadline(0), DAY);
+    }
+
+    #[test]
+    fn test_fund_after_deadline() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        assert!(contract.get_crowdfund_progress(0) > 0);
+
+        testing_env!(context.block_timestamp(DAY).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(0))), 500);
+        assert!(contract.crowdfunds.get(0).unwrap().is_out_of_time());
+    }
+
+    #[test]
+    fn test_views_after_deadline_emit_nothing() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.block_timestamp(DAY).build());
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::OutOfTime);
+        assert_eq!(contract.get_crowdfund(0).status, CrowdfundStatus::OutOfTime);
+        assert_eq!(contract.get_refund_amount(0, accounts(1)).0, 500);
+        contract.get_crowdfund_fees(0);
+        contract.get_contributions(accounts(1));
+
+        // Views report the status without saving it, so there's no event to announce
+        assert!(event_names().is_empty());
+        assert!(!contract.crowdfunds.get(0).unwrap().is_out_of_time());
+
+        // The first change saves it
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_refund(0);
+        assert_eq!(event_names(), vec!["crowdfund_status_changed"]);
+    }
+
+    #[test]
+    fn test_fee_bps() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_default_fee_bps(250);
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { fee_bps: Some(0), ..Default::default() }));
+
+        assert_eq!(contract.get_crowdfund_fee_bps(0), DEFAULT_FEE_BPS);
+        assert_eq!(contract.get_crowdfund_fee_bps(1), 250);
+        assert_eq!(contract.get_crowdfund_fee_bps(2), 0);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        for item_index in 0..3 {
+            contract.ft_on_transfer(accounts(1), U128::from(999), funding_msg(item_index));
+        }
+
+        // Fees are rounded down
+        assert_eq!(contract.get_crowdfund_progress(0), 999 - 39);
+        assert_eq!(contract.get_crowdfund_progress(1), 999 - 24);
+        assert_eq!(contract.get_crowdfund_progress(2), 999);
+    }
+
+    #[test]
+    fn test_migrate_legacy_state() {
+        let mut context = get_context(accounts(0));
         testing_env!(context.build());
-        let mut contract = Contract::new("test.near".parse().unwrap());
-        let sample_item_name = String::from("rolex");
-        contract.new_item(sample_item_name.clone(), 1000);
-        assert_eq!(contract.items.get(&0), Some(sample_item_name));
-        let the_vec: Vec<String> = vec!(String::from("rolex"));
-        assert_eq!(contract.get_current_items(), the_vec);
+
+        // Store a crowdfund and the contract in their layout from before the state was versioned
+        let nested_hash = env::sha256_array(&0u64.to_be_bytes());
+        let mut fundings: UnorderedMap<AccountId, Balance> = UnorderedMap::new(crowdfund::StorageKeys::Fundings { nested_hash });
+        let mut fees_paid: UnorderedMap<AccountId, Balance> = UnorderedMap::new(crowdfund::StorageKeys::FeesPaid { nested_hash });
+        fundings.insert(&accounts(1), &480);
+        fees_paid.insert(&accounts(1), &20);
+
+        let nft_account_id: AccountId = "nft.test.near".parse().unwrap();
+        let mut crowdfunds = Vector::new(StorageKeys::Crowdfunds);
+        crowdfunds.push(&(nft_account_id.clone(), 0u64, 4.0f64, sample_token_metadata(), 1000u128, fundings, fees_paid, 480u128, CrowdfundStatus::InProgress));
+
+        // A minted crowdfund was left transporting
+        let nested_hash = env::sha256_array(&1u64.to_be_bytes());
+        let mut fundings: UnorderedMap<AccountId, Balance> = UnorderedMap::new(crowdfund::StorageKeys::Fundings { nested_hash });
+        let mut fees_paid: UnorderedMap<AccountId, Balance> = UnorderedMap::new(crowdfund::StorageKeys::FeesPaid { nested_hash });
+        fundings.insert(&accounts(3), &1000);
+        fees_paid.insert(&accounts(3), &41);
+        crowdfunds.push(&(nft_account_id.clone(), 1u64, 4.0f64, sample_token_metadata(), 1000u128, fundings, fees_paid, 1000u128, CrowdfundStatus::Transporting));
+
+        let mut crowdfund_operators = Vector::new(StorageKeys::CrowdfundOperators);
+        crowdfund_operators.push(&accounts(2));
+        env::state_write(&(String::from("test"), DEFAULT_TOKEN_DECIMALS, accounts(4), nft_account_id, 4.0f64, crowdfunds, crowdfund_operators));
+
+        let migrated = Contract::migrate(Some(vec![MintedItem { item_index: 1, token_id: String::from("3") }]));
+        assert_eq!(migrated.get_state_version(), state::STATE_VERSION);
+        assert_eq!(migrated.get_default_fee_bps(), DEFAULT_FEE_BPS);
+        assert_eq!(migrated.get_crowdfund_fee_bps(0), DEFAULT_FEE_BPS);
+        assert_eq!(migrated.get_crowdfund_progress(0), 480);
+        assert_eq!(migrated.get_crowdfund_status(0), CrowdfundStatus::InProgress);
+        assert_eq!(migrated.get_accepted_coins(), vec![(accounts(4), DEFAULT_TOKEN_DECIMALS)]);
+        assert_eq!(migrated.get_owner(), accounts(0));
+        assert!(migrated.has_role(Role::Operator, accounts(2)));
+        assert_eq!(migrated.get_contributions(accounts(1))[0].fee.0, 20);
+        assert_eq!(migrated.get_crowdfund_status(1), CrowdfundStatus::Tokenized);
+        assert_eq!(migrated.get_crowdfund(1).token_id, Some(String::from("3")));
+
+        // Migrating the current version keeps the state as is
+        env::state_write(&migrated);
+        let mut migrated = Contract::migrate(None);
+        register_storage(&mut migrated, &mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        // 520 of the 960 netto fits, the fee on the other 440 is given back: 440 + (40 - 40 * 520 / 960)
+        assert_eq!(unused_amount(migrated.ft_on_transfer(accounts(3), U128::from(1000), funding_msg(0))), 459);
+        assert_eq!(migrated.get_crowdfund_progress(0), 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Caller does not have the Owner role.")]
+    fn test_upgrade_without_owner() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.upgrade();
+    }
+
+    #[test]
+    fn test_crowdfund_stored_as_latest_version() {
+        let mut context = get_context(accounts(1));
+        setup_contract(&mut context);
+
+        // Update this to the latest variant when adding one, so the tag written can't fall behind
+        let crowdfund_key = [StorageKeys::Crowdfunds.try_to_vec().unwrap(), 0u64.to_le_bytes().to_vec()].concat();
+        let crowdfund_bytes = env::storage_read(&crowdfund_key).unwrap();
+        let state::VersionedCrowdfund::V1(crowdfund) = state::VersionedCrowdfund::try_from_slice(&crowdfund_bytes).unwrap();
+        assert_eq!(crowdfund.get_goal(), 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas attached to deploy the code and migrate.")]
+    fn test_upgrade_without_enough_gas() {
+        let mut context = get_context(accounts(0));
+        let mut contract = setup_contract(&mut context);
+
+        let mut upgrade_context = context.predecessor_account_id(accounts(0)).prepaid_gas(Gas(20 * TGAS)).build();
+        upgrade_context.input = vec![0u8; 8];
+        testing_env!(upgrade_context);
+        contract.upgrade();
+    }
+
+    #[test]
+    fn test_contribution_limits() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_contribution_limits(0, ContributionLimits {
+            min_contribution: Some(U128::from(100)),
+            max_contribution: Some(U128::from(300)),
+            max_funders: Some(2),
+        });
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(50), funding_msg(0))), 50);
+
+        // Only the maximum contribution is used, with its part of the fee: 180 + (20 - 20 * 300 / 480)
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0))), 188);
+        assert_eq!(contract.get_contributions(accounts(1))[0].fee.0, 12);
+        assert_eq!(contract.get_crowdfund_progress(0), 300);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(200), funding_msg(0))), 200);
+
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(200), funding_msg(0))), 0);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(3), U128::from(200), funding_msg(0))), 200);
+        assert_eq!(contract.get_funders(0, None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Compliance, accounts(0));
+        contract.set_crowdfund_access(0, Access::Allowlist);
+        contract.add_to_allowlist(vec![accounts(1), accounts(2)]);
+        contract.remove_from_allowlist(vec![accounts(2)]);
+        assert_eq!(contract.get_allowlist(None, None), vec![accounts(1)]);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(0))), 500);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0))), 0);
+
+        // The beneficiary is checked, not the sender
+        let msg = json!({"item_index": 0, "beneficiary": accounts(1)}).to_string();
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(100), msg)), 0);
+        assert_eq!(contract.get_crowdfund_progress(0), 480 + 96);
+    }
+
+    #[test]
+    #[should_panic(expected = "Caller does not have the Compliance role.")]
+    fn test_allowlist_without_role() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        contract.add_to_allowlist(vec![accounts(1)]);
+    }
+
+    #[test]
+    fn test_registry_access() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_crowdfund_access(0, Access::Registry { registry_account_id: accounts(5) });
+
+        // Funding waits for the registry, and is only done when it verified the beneficiary
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert!(matches!(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0)), PromiseOrValue::Promise(_)));
+        assert_eq!(contract.get_crowdfund_progress(0), 0);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        assert_eq!(contract.fund_verified_callback(accounts(1), accounts(4), U128::from(500), funding_msg(0), accounts(1), Ok(false)).0, 500);
+        assert_eq!(contract.fund_verified_callback(accounts(1), accounts(4), U128::from(500), funding_msg(0), accounts(1), Err(PromiseError::Failed)).0, 500);
+        assert_eq!(contract.get_crowdfund_progress(0), 0);
+
+        assert_eq!(contract.fund_verified_callback(accounts(1), accounts(4), U128::from(500), funding_msg(0), accounts(1), Ok(true)).0, 0);
+        assert_eq!(contract.get_crowdfund_progress(0), 480);
+    }
+
+    #[test]
+    fn test_hard_cap() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { hard_cap: Some(U128::from(1200)), ..Default::default() }));
+
+        // Funding continues past the goal, up to the hard cap
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(1250), funding_msg(1));
+        assert_eq!(contract.get_crowdfund_progress(1), 1200);
+        assert_eq!(event_names(), vec!["crowdfund_funded", "crowdfund_goal_reached", "crowdfund_hard_cap_reached"]);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(1))), 100);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(1);
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::Buying);
+    }
+
+    #[test]
+    fn test_soft_cap_at_deadline() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { hard_cap: Some(U128::from(2000)), ..Default::default() }));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(1), U128::from(1250), funding_msg(1));
+
+        // At the deadline, the crowdfund below its goal runs out of time and the one above it succeeds
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(DAY).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(1))), 100);
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::OutOfTime);
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::InProgress);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(DAY).build());
+        contract.start_buying(1);
+        assert_eq!(contract.get_crowdfund_progress(1), 1200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Funding is still open until the hard cap or the deadline.")]
+    fn test_buy_before_funding_closed() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { hard_cap: Some(U128::from(2000)), ..Default::default() }));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(1250), funding_msg(1));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(1);
+    }
+
+    #[test]
+    fn test_fund_with_multiple_coins() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.add_accepted_coin(accounts(3), 18);
+        assert_eq!(contract.get_accepted_coins().len(), 2);
+
+        // 500 in the internal unit of 6 decimals, plus dust that can't be represented
+        let unit: u128 = 1_000_000_000_000;
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500 * unit + 7), funding_msg(0))), 7);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(2), U128::from(300), funding_msg(0));
+        assert_eq!(contract.get_crowdfund_progress(0), 480 + 288);
+
+        let coin_totals = contract.get_crowdfund_coin_totals(0);
+        assert_eq!(coin_totals.len(), 2);
+        let coin_total = coin_totals.iter().find(|coin_total| coin_total.coin_id == accounts(3)).unwrap();
+        assert_eq!(coin_total.netto.0 + coin_total.fee.0, 500 * unit);
+
+        // Coins that are no longer accepted can't fund
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.remove_accepted_coin(accounts(3));
+        assert_eq!(contract.get_accepted_coins().len(), 1);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500 * unit), funding_msg(0))), 500 * unit);
+        assert_eq!(contract.get_crowdfund_progress(0), 480 + 288);
+    }
+
+    #[test]
+    fn test_withdraw_fees() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_treasury(accounts(5));
+        contract.grant_role(Role::Treasurer, accounts(5));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(0));
+
+        let fees = contract.get_crowdfund_fees(0);
+        assert!(fees.collected.0 > 0);
+        assert_eq!(fees.refundable.0, fees.collected.0);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.nft_mint_callback(0, Ok("0".to_string()));
+
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        contract.withdraw_fees(0);
+
+        let fees = contract.get_crowdfund_fees(0);
+        assert_eq!(fees.withdrawn.0, fees.collected.0);
+        assert_eq!(fees.refundable.0, 0);
+
+        // A failed transfer makes the fees withdrawable again
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.withdraw_fees_callback(0, accounts(4), fees.withdrawn, Err(PromiseError::Failed));
+        assert_eq!(contract.get_platform_fees(None, None).withdrawn.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fees can only be withdrawn from tokenized items, or failed items that keep their fees.")]
+    fn test_withdraw_fees_before_tokenization() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.grant_role(Role::Treasurer, accounts(0));
+        contract.withdraw_fees(0);
+    }
+
+    #[test]
+    fn test_invalid_funding_messages() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), String::from("not json"))), 500);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(7))), 500);
+
+        let too_low_fee = json!({"item_index": 0, "max_fee_bps": 100}).to_string();
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), too_low_fee)), 500);
+
+        let overfunding = json!({"item_index": 0, "fill_exactly": true}).to_string();
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(2000), overfunding.clone())), 2000);
+
+        // The maximum contribution counts as well
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_contribution_limits(0, ContributionLimits { min_contribution: None, max_contribution: Some(U128::from(300)), max_funders: None });
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), overfunding)), 500);
+
+        assert_eq!(contract.get_crowdfund_progress(0), 0);
+    }
+
+    #[test]
+    fn test_fund_for_beneficiary() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        let msg = json!({"item_index": 0, "beneficiary": accounts(2), "max_fee_bps": 400}).to_string();
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), msg)), 0);
+
+        testing_env!(context.block_timestamp(DAY).build());
+        assert_eq!(contract.get_refund_amount(0, accounts(1)).0, 0);
+        assert_eq!(contract.get_refund_amount(0, accounts(2)).0, 500);
+    }
+
+    #[test]
+    fn test_get_contributions() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(100), funding_msg(0));
+        contract.ft_on_transfer(accounts(1), U128::from(100), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(1));
+
+        let contributions = contract.get_contributions(accounts(1));
+        assert_eq!(contributions.len(), 1);
+        assert_eq!(contributions[0].item_index, 0);
+        assert_eq!(contributions[0].netto.0, 192);
+        assert_eq!(contributions[0].fee.0, 8);
+        assert!(!contributions[0].refundable);
+
+        assert_eq!(contract.get_contributions(accounts(2)).len(), 2);
+        assert_eq!(contract.get_contributions(accounts(3)).len(), 0);
+
+        let funders = contract.get_funders(0, None, None);
+        assert_eq!(funders.iter().map(|funder| funder.account_id.clone()).collect::<Vec<AccountId>>(), vec![accounts(1), accounts(2)]);
+        assert_eq!(contract.get_funders(0, Some(1), Some(5)).len(), 1);
+
+        // Out of time: the contribution can be refunded, including the fee
+        testing_env!(context.block_timestamp(DAY).build());
+        let contributions = contract.get_contributions(accounts(1));
+        assert_eq!(contributions[0].status, CrowdfundStatus::OutOfTime);
+        assert!(contributions[0].refundable);
+        assert_eq!(contributions[0].refund_amount.0, 200);
+    }
+
+    #[test]
+    fn test_claim_refund() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(DAY).build());
+        contract.claim_refund(0);
+        assert!(contract.is_refunded(0, accounts(1)));
+
+        // A failed transfer makes the refund claimable again
+        contract.claim_refund_callback(0, accounts(1), accounts(4), U128::from(500), Err(PromiseError::Failed));
+        assert!(!contract.is_refunded(0, accounts(1)));
+    }
+
+    #[test]
+    fn test_refund_failed_buying() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { refund_fees: Some(false), ..Default::default() }));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(1));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        for item_index in 0..2 {
+            contract.start_buying(item_index);
+            contract.fail_buying(item_index);
+        }
+
+        // Fees are only given back when the crowdfund is configured to do so
+        assert!(contract.get_refund_amount(0, accounts(1)).0 > 1000);
+        assert_eq!(contract.get_refund_amount(1, accounts(1)).0, 1000);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_refund(1);
+        assert!(contract.is_refunded(1, accounts(1)));
+
+        // The fees that are not given back go to the treasury
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.grant_role(Role::Treasurer, accounts(0));
+        contract.withdraw_fees(1);
+        let fees = contract.get_crowdfund_fees(1);
+        assert_eq!(fees.withdrawn.0, fees.collected.0);
+        assert_eq!(fees.refundable.0, 0);
+
+        // The platform fees are summed page by page
+        let first_page = contract.get_platform_fees(None, Some(1));
+        let second_page = contract.get_platform_fees(Some(1), Some(1));
+        assert_eq!(first_page.withdrawn.0, 0);
+        assert_eq!(first_page.collected.0 + second_page.collected.0, contract.get_platform_fees(None, None).collected.0);
+        assert_eq!(contract.get_platform_fees(Some(2), None).collected.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Funding has already been refunded.")]
+    fn test_claim_refund_twice() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(DAY).build());
+        contract.claim_refund(0);
+        contract.claim_refund(0);
+    }
+
+    #[test]
+    fn test_cancel_crowdfund() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { refund_fees: Some(false), ..Default::default() }));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        for funder in [accounts(1), accounts(2), accounts(3)] {
+            contract.ft_on_transfer(funder, U128::from(200), funding_msg(1));
+        }
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.cancel_crowdfund(1, String::from("Item is no longer for sale."));
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::Rejected);
+        assert_eq!(event_names(), vec!["crowdfund_status_changed", "crowdfund_cancelled"]);
+
+        // Cancelled crowdfunds give back the fees, even when not configured to do so
+        assert_eq!(contract.get_refund_amount(1, accounts(1)).0, 200);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.claim_refund(1);
+
+        // Batches skip funders that claimed themselves
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        assert_eq!(contract.process_refunds(1, Some(1)), 2);
+        assert!(contract.is_refunded(1, accounts(1)));
+        assert!(!contract.is_refunded(1, accounts(3)));
+        assert_eq!(contract.process_refunds(1, None), 0);
+        assert!(contract.is_refunded(1, accounts(3)));
+
+        // A failed transfer of a batch makes the refund claimable again
+        contract.claim_refund_callback(1, accounts(3), accounts(4), U128::from(200), Err(PromiseError::Failed));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.claim_refund(1);
+        assert!(contract.is_refunded(1, accounts(3)));
+    }
+
+    #[test]
+    fn test_process_refunds_by_transfers() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.add_accepted_coin(accounts(3), DEFAULT_TOKEN_DECIMALS);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(100), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(0));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(100), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.cancel_crowdfund(0, String::from("Duplicate listing."));
+
+        // A funder paying in two coins is split over batches of one transfer
+        assert_eq!(contract.process_refunds(0, Some(1)), 2);
+        assert_eq!(contract.process_refunds(0, Some(1)), 1);
+        assert_eq!(contract.process_refunds(0, Some(2)), 0);
+        assert!(contract.is_refunded(0, accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "All refunds have been processed.")]
+    fn test_process_refunds_when_done() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.cancel_crowdfund(0, String::from("Duplicate listing."));
+        contract.process_refunds(0, None);
+        contract.process_refunds(0, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "This crowdfund can't be refunded.")]
+    fn test_process_refunds_in_progress() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.process_refunds(0, None);
+    }
+
+    #[test]
+    fn test_withdraw_contribution() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.add_accepted_coin(accounts(3), DEFAULT_TOKEN_DECIMALS);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(250), funding_msg(0));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(250), funding_msg(0));
+        assert_eq!(contract.get_crowdfund_progress(0), 480);
+
+        // The netto amount is taken from the coins in order, with the fee paid on it
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_contribution(0, U128::from(300));
+        assert_eq!(contract.get_crowdfund_progress(0), 180);
+        let contribution = &contract.get_contributions(accounts(1))[0];
+        assert_eq!((contribution.netto.0, contribution.fee.0), (180, 8));
+
+        // A failed transfer restores the funding
+        contract.withdraw_contribution_callback(0, accounts(1), accounts(4), CoinWithdrawal { netto: U128::from(60), fee: U128::from(2), referral: None, funded_at: None }, Err(PromiseError::Failed));
+        assert_eq!(contract.get_crowdfund_progress(0), 240);
+
+        contract.withdraw_contribution(0, U128::from(240));
+        assert_eq!(contract.get_crowdfund_progress(0), 0);
+        assert_eq!(contract.get_crowdfund(0).funders, 0);
+        assert!(contract.get_crowdfund_coin_totals(0).iter().all(|coin_total| coin_total.netto.0 == 0 && coin_total.fee.0 == 0));
+    }
+
+    #[test]
+    fn test_rollback_withdrawal_at_hard_cap() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_withdrawal_window(0, Some(DAY / 4));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(DAY / 2).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        // A failed transfer restores the funding with when it was funded, for the cool-off window
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(5 * DAY / 8).build());
+        contract.withdraw_contribution(0, U128::from(480));
+        assert_eq!(contract.get_crowdfund(0).funders, 0);
+        contract.withdraw_contribution_callback(0, accounts(1), accounts(4), CoinWithdrawal { netto: U128::from(480), fee: U128::from(20), referral: None, funded_at: Some(DAY / 2) }, Err(PromiseError::Failed));
+        assert!(contract.get_contributions(accounts(1))[0].withdrawable);
+
+        // The pending withdrawal keeps its room under the hard cap
+        contract.withdraw_contribution(0, U128::from(480));
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(2), U128::from(1100), funding_msg(0));
+        assert_eq!(contract.get_crowdfund_progress(0), 520);
+
+        contract.withdraw_contribution_callback(0, accounts(1), accounts(4), CoinWithdrawal { netto: U128::from(480), fee: U128::from(20), referral: None, funded_at: Some(DAY / 2) }, Err(PromiseError::Failed));
+        assert_eq!(contract.get_crowdfund_progress(0), 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "The cool-off window has passed.")]
+    fn test_withdraw_after_cool_off() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_withdrawal_window(0, Some(DAY / 4));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(DAY / 8).build());
+        assert!(contract.get_contributions(accounts(1))[0].withdrawable);
+        contract.withdraw_contribution(0, U128::from(100));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(DAY / 2).build());
+        assert!(!contract.get_contributions(accounts(1))[0].withdrawable);
+        contract.withdraw_contribution(0, U128::from(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contributions can only be withdrawn before the goal is reached.")]
+    fn test_withdraw_after_goal() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_contribution(0, U128::from(100));
+    }
+
+    #[test]
+    fn test_transfer_position() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(1000), funding_msg(0));
+
+        // The fee record moves along with the netto amount
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(3), U128::from(120));
+        assert_eq!(event_names(), vec!["crowdfund_position_transferred"]);
+
+        let funders = contract.get_funders(0, None, None);
+        let funder = |account_id: AccountId| funders.iter().find(|funder| funder.account_id == account_id).map(|funder| (funder.netto.0, funder.fee.0));
+        assert_eq!(funder(accounts(1)), Some((360, 15)));
+        assert_eq!(funder(accounts(3)), Some((120, 5)));
+        assert_eq!(contract.get_crowdfund_progress(0), 1000);
+        assert_eq!(contract.get_contributions(accounts(3))[0].netto.0, 120);
+
+        // The receiver pays for the storage of its position
+        let min_balance = contract.storage_balance_bounds().min.0;
+        assert!(storage_available(&contract, accounts(3)) < STORAGE_DEPOSIT - min_balance);
+
+        // Transferring the whole position leaves the sender without one, and gives back its storage
+        let available = storage_available(&contract, accounts(1));
+        contract.transfer_position(0, accounts(3), U128::from(360));
+        assert!(storage_available(&contract, accounts(1)) > available);
+        assert_eq!(contract.get_crowdfund(0).funders, 2);
+        assert_eq!(contract.get_contributions(accounts(3))[0].netto.0, 480);
+        assert_eq!(contract.get_position(0, accounts(3)).0, 480);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer would take bob above the maximum contribution.")]
+    fn test_transfer_position_above_max_contribution() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(1000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_contribution_limits(0, ContributionLimits { min_contribution: None, max_contribution: Some(U128::from(600)), max_funders: None });
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(1), U128::from(200));
+    }
+
+    #[test]
+    #[should_panic(expected = "Maximum of 2 funders reached.")]
+    fn test_transfer_position_above_max_funders() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(1000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_contribution_limits(0, ContributionLimits { min_contribution: None, max_contribution: None, max_funders: Some(2) });
+
+        // Moving a whole position keeps the number of funders, moving part of it adds one
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(3), U128::from(480));
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(5), U128::from(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Positions can only be transferred once the goal is reached, until the item is tokenized.")]
+    fn test_transfer_position_before_goal() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(3), U128::from(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not on the allowlist.")]
+    fn test_transfer_position_outside_allowlist() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Compliance, accounts(0));
+        contract.add_to_allowlist(vec![accounts(1)]);
+        contract.set_crowdfund_access(0, Access::Allowlist);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(3), U128::from(100));
+    }
+
+    #[test]
+    fn test_operator_transitions() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::InProgress);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::Transporting);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.nft_mint_callback(0, Ok("0".to_string()));
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::Tokenized);
+    }
+
+    #[test]
+    #[should_panic(expected = "Goal already reached, the crowdfund can't run out of time.")]
+    fn test_out_of_time_after_goal() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.operator_transition(0, CrowdfundStatus::OutOfTime);
+    }
+
+    #[test]
+    fn test_get_crowdfunds() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+        contract.new_item(sample_token_metadata(), 1000, DAY, None);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(100), funding_msg(1));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.nft_mint_callback(0, Ok("7".to_string()));
+
+        assert_eq!(contract.get_crowdfunds(None, None, None).len(), 3);
+
+        let page = contract.get_crowdfunds(Some(1), Some(1), None);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].item_index, 1);
+        assert_eq!(page[0].funders, 1);
+        assert!(page[0].progress.0 > 0);
+        assert_eq!(page[0].token_id, None);
+
+        let tokenized = contract.get_crowdfunds(None, None, Some(CrowdfundStatus::Tokenized));
+        assert_eq!(tokenized.len(), 1);
+        assert_eq!(tokenized[0].item_index, 0);
+        assert_eq!(tokenized[0].token_id, Some("7".to_string()));
+
+        // Crowdfunds that ran out of time are listed as such
+        testing_env!(context.block_timestamp(DAY).build());
+        let out_of_time = contract.get_crowdfunds(None, None, Some(CrowdfundStatus::OutOfTime));
+        assert_eq!(out_of_time.iter().map(|view| view.item_index).collect::<Vec<u64>>(), vec![1, 2]);
+
+        // The limit applies to the crowdfunds scanned, not to those returned
+        assert!(contract.get_crowdfunds(None, Some(1), Some(CrowdfundStatus::OutOfTime)).is_empty());
+        assert_eq!(contract.get_crowdfunds(Some(1), Some(1), Some(CrowdfundStatus::OutOfTime)).len(), 1);
+    }
+
+    #[test]
+    fn test_lifecycle_events() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        assert_eq!(event_names(), vec!["crowdfund_created"]);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        contract.ft_on_transfer(accounts(2), U128::from(1000), funding_msg(0));
+        assert_eq!(event_names(), vec!["crowdfund_funded", "crowdfund_funded", "crowdfund_goal_reached"]);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.nft_mint_callback(0, Ok("0".to_string()));
+        assert_eq!(event_names(), vec!["crowdfund_status_changed", "crowdfund_status_changed", "crowdfund_status_changed", "crowdfund_tokenized"]);
+    }
+
+    #[test]
+    fn test_failed_tokenization() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+
+        contract.tokenize(0);
+        for _ in 1..DEFAULT_MAX_TOKENIZATION_ATTEMPTS {
+            contract.nft_mint_callback(0, Err(PromiseError::Failed));
+            contract.retry_tokenization(0);
+        }
+        contract.nft_mint_callback(0, Err(PromiseError::Failed));
+
+        assert_eq!(contract.get_failed_tokenizations(0), DEFAULT_MAX_TOKENIZATION_ATTEMPTS);
+        assert!(contract.get_tokenization_error(0).is_some());
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::Transporting);
+
+        contract.abandon_tokenization(0);
+        assert_eq!(contract.get_crowdfund_status(0), CrowdfundStatus::FailedTransporting);
+        assert!(contract.get_refund_amount(0, accounts(1)).0 > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokenization can't be abandoned yet.")]
+    fn test_abandon_tokenization_too_early() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.nft_mint_callback(0, Err(PromiseError::Failed));
+        contract.abandon_tokenization(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Item is being tokenized.")]
+    fn test_abandon_tokenization_during_retry() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        for _ in 0..DEFAULT_MAX_TOKENIZATION_ATTEMPTS {
+            contract.nft_mint_callback(0, Err(PromiseError::Failed));
+            contract.retry_tokenization(0);
+        }
+
+        // The retried nft_mint can still succeed, so the item can't be given up on yet
+        contract.abandon_tokenization(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Item is being tokenized.")]
+    fn test_fail_transporting_during_tokenization() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.fail_transporting(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Crowdfund can't go from InProgress to Transporting.")]
+    fn test_invalid_transition() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.start_transporting(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Item is not yet in the warehouse.")]
+    fn test_tokenize_before_warehouse() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(2000), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.tokenize(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "This crowdfund can't be refunded.")]
+    fn test_claim_refund_before_deadline() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_refund(0);
+    }
+
+    fn storage_available(contract: &Contract, account_id: AccountId) -> Balance {
+        contract.storage_balance_of(account_id).expect("Not registered").available.0
+    }
+
+    #[test]
+    fn test_storage_management() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        let min_balance = contract.storage_balance_bounds().min.0;
+        assert_eq!(storage_available(&contract, accounts(1)), STORAGE_DEPOSIT - min_balance);
+
+        // The operator paid for the crowdfund it created
+        assert!(storage_available(&contract, accounts(0)) < STORAGE_DEPOSIT - min_balance);
+
+        // Funders pay for their funding, and get it back when withdrawing. The record of having contributed stays
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        let available_funded = storage_available(&contract, accounts(1));
+        assert!(available_funded < STORAGE_DEPOSIT - min_balance);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_contribution(0, U128::from(480));
+        let available = storage_available(&contract, accounts(1));
+        assert!(available > available_funded);
+
+        // Only the available part of the deposit can be withdrawn
+        testing_env!(context.attached_deposit(1).build());
+        assert_eq!(contract.storage_withdraw(None).available.0, 0);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total.0, STORAGE_DEPOSIT - available);
+
+        // Unregistered accounts can't fund
+        let funder: AccountId = "new-funder.test.near".parse().unwrap();
+        testing_env!(context.predecessor_account_id(accounts(4)).attached_deposit(0).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(funder.clone(), U128::from(500), funding_msg(0))), 500);
+
+        // Registering only keeps the minimum balance
+        testing_env!(context.predecessor_account_id(funder.clone()).attached_deposit(STORAGE_DEPOSIT).build());
+        assert_eq!(contract.storage_deposit(None, Some(true)).total.0, min_balance);
+
+        testing_env!(context.predecessor_account_id(accounts(5)).attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(accounts(5)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough storage deposit for new-funder.test.near")]
+    fn test_fund_without_storage_deposit() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        let funder: AccountId = "new-funder.test.near".parse().unwrap();
+        testing_env!(context.predecessor_account_id(funder.clone()).attached_deposit(STORAGE_DEPOSIT).build());
+        contract.storage_deposit(None, Some(true));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).attached_deposit(0).build());
+        contract.ft_on_transfer(funder, U128::from(500), funding_msg(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't unregister while paying for the storage of fundings or items.")]
+    fn test_unregister_with_funding() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.storage_unregister(None);
+    }
+
+    #[test]
+    fn test_refund_releases_storage() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        let available = storage_available(&contract, accounts(1));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(0));
+        let available_funded = storage_available(&contract, accounts(1));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.cancel_crowdfund(0, String::from("Item is no longer for sale."));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_refund(0);
+
+        // Once the refund went through, the funding per coin is forgotten and its storage given back
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.claim_refund_callback(0, accounts(1), accounts(4), U128::from(500), Ok(()));
+        assert!(storage_available(&contract, accounts(1)) > available_funded);
+        assert!(storage_available(&contract, accounts(1)) <= available);
+        assert_eq!(contract.get_refund_amount(0, accounts(1)).0, 500);
+        assert!(contract.get_contributions(accounts(1))[0].refunded);
+    }
+
+    fn referral_msg(item_index: u64, referrer_id: AccountId) -> String {
+        json!({"item_index": item_index, "referrer": referrer_id}).to_string()
+    }
+
+    #[test]
+    fn test_referral_rewards() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_referral_bps(0, 2000);
+        contract.set_treasury(accounts(5));
+        contract.grant_role(Role::Treasurer, accounts(5));
+
+        // A funder stays with its first referrer, and can't refer itself
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), referral_msg(0, accounts(3)));
+        contract.ft_on_transfer(accounts(1), U128::from(200), referral_msg(0, accounts(5)));
+        contract.ft_on_transfer(accounts(2), U128::from(500), referral_msg(0, accounts(2)));
+        assert_eq!(contract.get_referrer(0, accounts(1)), Some(accounts(3)));
+        assert_eq!(contract.get_referrer(0, accounts(2)), None);
+
+        let referral = contract.get_referral(0, accounts(3));
+        assert_eq!((referral.volume.0, referral.reward.0), (672, 5));
+        assert!(!referral.claimable);
+        assert_eq!(contract.get_referral(0, accounts(5)).volume.0, 0);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.start_buying(0);
+        contract.start_transporting(0);
+        contract.confirm_in_warehouse(0);
+        contract.tokenize(0);
+        contract.nft_mint_callback(0, Ok("0".to_string()));
+
+        // The referral rewards are not withdrawn to the treasury
+        testing_env!(context.predecessor_account_id(accounts(5)).build());
+        contract.withdraw_fees(0);
+        let fees = contract.get_crowdfund_fees(0);
+        assert_eq!(fees.referral_rewards.0, 5);
+        assert_eq!(fees.withdrawn.0, fees.collected.0 - 5);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        assert!(contract.get_referral(0, accounts(3)).claimable);
+        contract.claim_referral_rewards(0);
+        assert_eq!(contract.get_referral(0, accounts(3)).claimed.0, 5);
+
+        // A failed transfer makes the rewards claimable again
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.claim_referral_rewards_callback(0, accounts(3), accounts(4), U128::from(5), Err(PromiseError::Failed));
+        assert!(contract.get_referral(0, accounts(3)).claimable);
+
+        contract.claim_referral_rewards_callback(0, accounts(3), accounts(4), U128::from(5), Ok(()));
+        assert_eq!(event_names(), vec!["crowdfund_referral_reward_claimed"]);
+    }
+
+    #[test]
+    fn test_referral_follows_position() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_referral_bps(0, 2000);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), referral_msg(0, accounts(3)));
+
+        // The credit of the referrer is taken back in proportion to what is withdrawn
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_contribution(0, U128::from(120));
+        let referral = contract.get_referral(0, accounts(3));
+        assert_eq!((referral.volume.0, referral.reward.0), (360, 3));
+
+        // A failed transfer gives the credit back
+        let reversed = ReversedReferral { referrer_id: accounts(3), volume: U128::from(120), reward: U128::from(1) };
+        contract.withdraw_contribution_callback(0, accounts(1), accounts(4), CoinWithdrawal { netto: U128::from(120), fee: U128::from(5), referral: Some(reversed), funded_at: None }, Err(PromiseError::Failed));
+        let referral = contract.get_referral(0, accounts(3));
+        assert_eq!((referral.volume.0, referral.reward.0), (480, 4));
+        assert_eq!(contract.get_crowdfund_fees(0).referral_rewards.0, 4);
+
+        contract.withdraw_contribution(0, U128::from(120));
+        contract.withdraw_contribution_callback(0, accounts(1), accounts(4), CoinWithdrawal { netto: U128::from(120), fee: U128::from(5), referral: None, funded_at: None }, Ok(()));
+
+        // The receiver of a position is not credited to the referrer
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(2), U128::from(1000), funding_msg(0));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.transfer_position(0, accounts(2), U128::from(360));
+        assert_eq!(contract.get_referral(0, accounts(3)).reward.0, 0);
+        assert_eq!(contract.get_referrer(0, accounts(1)), None);
+        assert_eq!(contract.get_referrer(0, accounts(2)), None);
+        assert_eq!(contract.get_crowdfund_fees(0).referral_rewards.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Referral rewards can only be claimed from tokenized items.")]
+    fn test_referral_rewards_forfeited_on_refund() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_referral_bps(0, 2000);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128::from(500), referral_msg(0, accounts(3)));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(DAY).build());
+        contract.claim_refund(0);
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(DAY).build());
+        contract.claim_refund_callback(0, accounts(1), accounts(4), U128::from(500), Ok(()));
+        assert_eq!(contract.get_referral(0, accounts(3)).reward.0, 0);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(DAY).build());
+        contract.claim_referral_rewards(0);
+    }
+
+    fn launch_schedule(start_timestamp: u64, early_access_timestamp: Option<u64>) -> LaunchSchedule {
+        LaunchSchedule { start_timestamp, early_access_timestamp }
+    }
+
+    #[test]
+    fn test_scheduled_launch() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, 4 * DAY, Some(NewItemOptions { launch: Some(launch_schedule(DAY, None)), ..Default::default() }));
+        assert_eq!(contract.get_crowdfund(1).status, CrowdfundStatus::Created);
+        assert_eq!(contract.get_launch_schedule(1).unwrap().start_timestamp, DAY);
+
+        // Visible but not fundable before the start
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(1))), 500);
+
+        // The first funding after the start opens the crowdfund
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(DAY).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(1))), 0);
+        assert_eq!(event_names(), vec!["crowdfund_status_changed", "crowdfund_funded"]);
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::InProgress);
+        assert_eq!(contract.get_crowdfund_progress(1), 480);
+    }
+
+    #[test]
+    fn test_early_access() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.grant_role(Role::Compliance, accounts(0));
+        contract.add_to_allowlist(vec![accounts(2)]);
+        contract.new_item(sample_token_metadata(), 1000, 4 * DAY, Some(NewItemOptions { launch: Some(launch_schedule(2 * DAY, Some(DAY))), ..Default::default() }));
+        contract.add_early_access(1, vec![accounts(1), accounts(3)]);
+        contract.remove_early_access(1, vec![accounts(3)]);
+        assert!(contract.has_early_access(1, accounts(1)));
+        assert!(!contract.has_early_access(1, accounts(3)));
+
+        // Early access is apart from the compliance allowlist
+        assert!(!contract.is_allowlisted(accounts(1)));
+        assert!(!contract.has_early_access(0, accounts(1)));
+
+        // Early access isn't open yet
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(1))), 500);
+
+        // Only accounts with early access can fund during early access, the crowdfund stays created
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(DAY).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(1))), 500);
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(1), U128::from(500), funding_msg(1))), 0);
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::Created);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(2 * DAY).build());
+        assert_eq!(unused_amount(contract.ft_on_transfer(accounts(2), U128::from(500), funding_msg(1))), 0);
+        assert_eq!(contract.get_crowdfund_progress(1), 960);
+    }
+
+    #[test]
+    fn test_start_scheduled_crowdfund_early() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, 4 * DAY, Some(NewItemOptions { launch: Some(launch_schedule(DAY, None)), ..Default::default() }));
+
+        contract.set_launch_schedule(1, launch_schedule(2 * DAY, None));
+        assert_eq!(contract.get_crowdfund(1).launch.unwrap().start_timestamp, 2 * DAY);
+
+        contract.start_crowdfund(1);
+        assert_eq!(contract.get_crowdfund_status(1), CrowdfundStatus::InProgress);
+    }
+
+    #[test]
+    #[should_panic(expected = "Start timestamp is after the end timestamp.")]
+    fn test_launch_after_deadline() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.new_item(sample_token_metadata(), 1000, DAY, Some(NewItemOptions { launch: Some(launch_schedule(2 * DAY, None)), ..Default::default() }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only created crowdfunds can be scheduled.")]
+    fn test_schedule_started_crowdfund() {
+        let mut context = get_context(accounts(1));
+        let mut contract = setup_contract(&mut context);
+        contract.set_launch_schedule(0, launch_schedule(DAY / 2, None));
     }
 }
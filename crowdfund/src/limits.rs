@@ -0,0 +1,71 @@
+//! Module for the limits on contributions to a crowdfund.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+// Limits on the netto amounts (in the internal unit, excluding fees) funded on a crowdfund
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContributionLimits {
+    // The smallest contribution, unless it's what is left to reach the hard cap
+    pub min_contribution: Option<U128>,
+
+    // The most an account can contribute in total
+    pub max_contribution: Option<U128>,
+
+    // The most accounts that can fund the crowdfund
+    pub max_funders: Option<u64>
+}
+
+impl ContributionLimits {
+    pub fn assert_valid(&self) {
+        if let (Some(min_contribution), Some(max_contribution)) = (self.min_contribution, self.max_contribution) {
+            require!(min_contribution.0 <= max_contribution.0, "Minimum contribution is above the maximum.");
+        }
+
+        require!(self.max_funders != Some(0), "At least one funder must be allowed.");
+    }
+
+    // The netto amount an account can still add. Contributions breaking the limits get an error
+    pub fn room_for(&self, funded_by_account: u128, funder_count: u64, netto_amount: u128, remaining_cap: u128) -> Result<u128, String> {
+        if let Some(max_funders) = self.max_funders {
+            if funded_by_account == 0 && funder_count >= max_funders {
+                return Err(format!("Maximum of {} funders reached.", max_funders));
+            }
+        }
+
+        let room = match self.max_contribution {
+            Some(max_contribution) if funded_by_account >= max_contribution.0 => {
+                return Err(format!("Maximum contribution of {} reached.", max_contribution.0));
+            },
+            Some(max_contribution) => max_contribution.0 - funded_by_account,
+            None => u128::MAX,
+        };
+
+        if let Some(min_contribution) = self.min_contribution {
+            if netto_amount < min_contribution.0 && netto_amount < remaining_cap {
+                return Err(format!("Contribution is below the minimum of {}.", min_contribution.0));
+            }
+        }
+
+        Ok(room)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Change the limits of a crowdfund, existing contributions are kept
+    pub fn set_contribution_limits(&mut self, item_index: u64, limits: ContributionLimits) {
+        self.assert_role(Role::Operator);
+        limits.assert_valid();
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.set_limits(limits);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_contribution_limits(&self, item_index: u64) -> ContributionLimits {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_limits()
+    }
+}
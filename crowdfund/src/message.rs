@@ -0,0 +1,33 @@
+//! Module for the message attached to a funding transfer (ft_transfer_call).
+
+use near_sdk::AccountId;
+use near_sdk::serde::Deserialize;
+use near_sdk::serde_json;
+
+// The msg of ft_transfer_call when funding a crowdfund, e.g. {"item_index": 0, "max_fee_bps": 500}
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FundingMessage {
+    // The index of the crowdfund to fund
+    pub item_index: u64,
+
+    // The account the funding is performed for, the sender if not given
+    pub beneficiary: Option<AccountId>,
+
+    // The account that referred the beneficiary, which is credited a share of the fee
+    #[serde(alias = "referrer")]
+    pub referral: Option<AccountId>,
+
+    // The highest fee (in basis points) the sender accepts
+    pub max_fee_bps: Option<u16>,
+
+    // Only fund if the whole amount can be used, instead of returning a leftover
+    #[serde(default)]
+    pub fill_exactly: bool
+}
+
+impl FundingMessage {
+    pub fn parse(msg: &str) -> Result<Self, String> {
+        serde_json::from_str(msg).map_err(|error| format!("Invalid funding message: {}", error))
+    }
+}
@@ -0,0 +1,136 @@
+//! Module for upgrading the contract code and migrating the contract state to the new layout.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crowdfund::{percentage_to_bps, LegacyCrowdfund};
+use state::STATE_VERSION;
+
+// Gas kept for deploying the new code, the rest is given to migrate
+const UPGRADE_GAS_RESERVE: u64 = 30 * TGAS;
+
+// Layout of the contract before the state was versioned, as deployed on testnet
+#[derive(BorshDeserialize)]
+pub struct LegacyContract {
+    base_uri: String,
+    decimals: u8,
+    accepted_coin: AccountId,
+    nft_account_id: AccountId,
+    default_fee_percentage: f64,
+    crowdfunds: Vector<LegacyCrowdfund>,
+    crowdfund_operators: Vector<AccountId>
+}
+
+// A legacy crowdfund that was already minted, with the token minted for it. The legacy layout left it Transporting
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintedItem {
+    pub item_index: u64,
+    pub token_id: TokenId
+}
+
+#[near_bindgen]
+impl Contract {
+    // Deploy the code passed as input and migrate the state. The input is the raw wasm, not JSON
+    pub fn upgrade(&mut self) -> Promise {
+        self.assert_role(Role::Owner);
+        let code = env::input().expect("No code to deploy.");
+
+        let migrate_gas = env::prepaid_gas().0
+            .checked_sub(env::used_gas().0)
+            .and_then(|gas| gas.checked_sub(UPGRADE_GAS_RESERVE))
+            .filter(|gas| *gas > 0)
+            .expect("Not enough gas attached to deploy the code and migrate.");
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                String::from("migrate"),
+                json!({}).to_string().into_bytes(),
+                0,
+                Gas(migrate_gas),
+            )
+    }
+
+    // Read the state in the layout it was written in, and rewrite it in the current layout.
+    // The legacy layout didn't save minted crowdfunds as tokenized, so they are passed when migrating from it
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(minted_items: Option<Vec<MintedItem>>) -> Self {
+        let contract = match state::read_state_version() {
+            0 => Self::from_legacy(env::state_read().expect("Failed to read legacy state"), minted_items.unwrap_or_default()),
+            STATE_VERSION => {
+                require!(minted_items.is_none(), "Minted items can only be passed when migrating legacy state.");
+                env::state_read().expect("Failed to read state")
+            },
+            version => env::panic_str(&format!("Can't migrate from state version {}.", version)),
+        };
+
+        state::write_state_version();
+        contract
+    }
+
+    pub fn get_state_version(&self) -> u16 {
+        state::read_state_version()
+    }
+}
+
+impl Contract {
+    fn from_legacy(mut old: LegacyContract, minted_items: Vec<MintedItem>) -> Self {
+        let mut coins = UnorderedMap::new(StorageKeys::Coins);
+        coins.insert(&old.accepted_coin, &Coin { decimals: old.decimals, accepted: true });
+
+        // Crowdfunds are rewritten in place: each one is read before it is overwritten at the same index
+        let mut crowdfunds = CrowdfundVector::new(StorageKeys::Crowdfunds);
+        for item_index in 0..old.crowdfunds.len() {
+            let old_crowdfund = old.crowdfunds.get(item_index).expect("Missing crowdfund!");
+            crowdfunds.push(&Crowdfund::from_legacy(old_crowdfund, &old.accepted_coin));
+        }
+
+        log!("Migrated {} legacy crowdfunds.", crowdfunds.len());
+
+        for minted_item in minted_items {
+            let mut crowdfund = crowdfunds.get(minted_item.item_index).expect("Incorrect item index!");
+            crowdfund.finish_legacy_tokenization(minted_item.token_id);
+            crowdfunds.replace(minted_item.item_index, &crowdfund);
+        }
+
+        let mut contract = Self {
+            base_uri: old.base_uri,
+            decimals: old.decimals,
+            coins,
+            nft_account_id: old.nft_account_id,
+            default_fee_bps: percentage_to_bps(old.default_fee_percentage),
+            treasury_account_id: env::current_account_id(),
+            crowdfunds,
+            max_tokenization_attempts: DEFAULT_MAX_TOKENIZATION_ATTEMPTS,
+            owner_id: env::current_account_id(),
+            pending_owner_id: None,
+            roles: LookupMap::new(StorageKeys::Roles),
+            contributions: LookupMap::new(StorageKeys::Contributions),
+            paused: BTreeSet::new(),
+            allowlist: UnorderedSet::new(StorageKeys::Allowlist),
+            storage_accounts: LookupMap::new(StorageKeys::StorageAccounts),
+            account_storage_usage: storage::measure_account_storage_usage(),
+        };
+
+        // Operators become members of the operator role
+        let mut operators = UnorderedSet::new(StorageKeys::RoleMembers { role: Role::Operator });
+        for operator in old.crowdfund_operators.iter() {
+            operators.insert(&operator);
+        }
+        contract.roles.insert(&Role::Operator, &operators);
+        old.crowdfund_operators.clear();
+
+        // Index the contributions made before they were kept track of
+        for item_index in 0..contract.crowdfunds.len() {
+            let crowdfund = contract.crowdfunds.get(item_index).expect("Missing crowdfund!");
+            for funder in crowdfund.get_funders(0, crowdfund.get_funder_count()) {
+                contract.record_contribution(&funder, item_index);
+            }
+        }
+
+        contract
+    }
+}
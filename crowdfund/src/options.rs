@@ -0,0 +1,31 @@
+//! Module for the optional settings of a new crowdfund, passed to new_item.
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::access::Access;
+use crate::launch::LaunchSchedule;
+use crate::limits::ContributionLimits;
+
+// The settings of new_item that have a default, e.g. {"fee_bps": 250, "hard_cap": "1500"}
+#[derive(Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewItemOptions {
+    // Whether refunds give back the fees, true if not given
+    pub refund_fees: Option<bool>,
+
+    // The fee in basis points, the default fee if not given
+    pub fee_bps: Option<u16>,
+
+    // Limits on the contributions, none if not given
+    pub limits: Option<ContributionLimits>,
+
+    // Who can fund, everyone if not given
+    pub access: Option<Access>,
+
+    // The most that can be funded, the goal if not given
+    pub hard_cap: Option<U128>,
+
+    // When the crowdfund opens for funding, at once if not given
+    pub launch: Option<LaunchSchedule>
+}
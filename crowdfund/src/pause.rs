@@ -0,0 +1,57 @@
+//! Module for pausing parts of the contract in an emergency.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+use events::{CrowdfundEvent, PauseData};
+
+// The parts of the contract that can be paused independently
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Feature {
+    // Funding crowdfunds through ft_on_transfer, paused fundings are returned as unused
+    Funding,
+
+    // Creating new crowdfunds
+    ItemCreation,
+
+    // Tokenizing items, including retries
+    Tokenization,
+
+    // Transferring crowdfund positions between accounts
+    PositionTransfers
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn pause(&mut self, feature: Feature) {
+        self.assert_role(Role::Pauser);
+
+        if self.paused.insert(feature) {
+            CrowdfundEvent::Pause(vec![PauseData { feature, account_id: env::predecessor_account_id() }]).emit();
+        }
+    }
+
+    pub fn unpause(&mut self, feature: Feature) {
+        self.assert_role(Role::Pauser);
+
+        if self.paused.remove(&feature) {
+            CrowdfundEvent::Unpause(vec![PauseData { feature, account_id: env::predecessor_account_id() }]).emit();
+        }
+    }
+
+    pub fn is_paused(&self, feature: Feature) -> bool {
+        self.paused.contains(&feature)
+    }
+
+    pub fn get_paused_features(&self) -> Vec<Feature> {
+        self.paused.iter().copied().collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_not_paused(&self, feature: Feature) {
+        require!(!self.is_paused(feature), format!("{:?} is paused.", feature));
+    }
+}
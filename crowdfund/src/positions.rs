@@ -0,0 +1,77 @@
+//! Module for transferring crowdfund positions between accounts before the item is tokenized.
+
+use crate::*;
+
+use access::{ext_registry, REGISTRY_GAS};
+
+const TRANSFER_POSITION_CALLBACK_GAS: u64 = 10 * TGAS;
+
+#[near_bindgen]
+impl Contract {
+    // Transfer part of the position of the caller (netto, in the internal unit) with the fee paid on it. Requires 1 yoctoNEAR, like token transfers
+    #[payable]
+    pub fn transfer_position(&mut self, item_index: u64, receiver_id: AccountId, amount: U128) -> PromiseOrValue<U128> {
+        assert_one_yocto();
+        self.assert_not_paused(Feature::PositionTransfers);
+
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        let sender_id = env::predecessor_account_id();
+
+        // Restricted crowdfunds can only be transferred to accounts that are allowed to fund them
+        match crowdfund.get_access() {
+            Access::Allowlist => {
+                require!(self.allowlist.contains(&receiver_id), format!("Account {} is not on the allowlist.", receiver_id));
+            },
+            Access::Registry { registry_account_id } => {
+                return PromiseOrValue::Promise(
+                    ext_registry::ext(registry_account_id)
+                        .with_static_gas(Gas(REGISTRY_GAS))
+                        .is_verified(receiver_id.clone())
+                        .then(
+                            Self::ext(env::current_account_id())
+                                .with_static_gas(Gas(TRANSFER_POSITION_CALLBACK_GAS))
+                                .transfer_position_callback(item_index, sender_id, receiver_id, amount)
+                        )
+                );
+            },
+            Access::Public => {}
+        }
+
+        self.internal_transfer_position(item_index, &sender_id, &receiver_id, amount.0);
+        PromiseOrValue::Value(amount)
+    }
+
+    // The netto funding of the account, which is its share of the item token once tokenized
+    pub fn get_position(&self, item_index: u64, account_id: AccountId) -> U128 {
+        let (netto, _) = self.crowdfunds.get(item_index).expect("Incorrect item index!").get_contribution(&account_id);
+        U128::from(netto)
+    }
+
+    // Transfer the position after the registry verified the receiver
+    #[private]
+    pub fn transfer_position_callback(&mut self, item_index: u64, sender_id: AccountId, receiver_id: AccountId, amount: U128, #[callback_result] call_result: Result<bool, PromiseError>) -> U128 {
+        require!(matches!(call_result, Ok(true)), format!("Account {} is not verified by the registry.", receiver_id));
+        self.assert_not_paused(Feature::PositionTransfers);
+
+        self.internal_transfer_position(item_index, &sender_id, &receiver_id, amount.0);
+        amount
+    }
+}
+
+impl Contract {
+    // The sender gets back the storage it frees, the receiver pays for the storage it adds
+    fn internal_transfer_position(&mut self, item_index: u64, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        require!(self.is_storage_registered(receiver_id), format!("Account {} is not registered for storage.", receiver_id));
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+
+        let initial_storage = env::storage_usage();
+        let transfers = crowdfund.start_position_transfer(sender_id, receiver_id, amount);
+        self.release_storage(sender_id, initial_storage);
+
+        let initial_storage = env::storage_usage();
+        crowdfund.complete_position_transfer(sender_id, receiver_id, transfers);
+        self.crowdfunds.replace(item_index, &crowdfund);
+        self.record_contribution(receiver_id, item_index);
+        self.charge_storage(receiver_id, initial_storage);
+    }
+}
@@ -0,0 +1,138 @@
+//! Module for referral rewards: referrers get a share of the fees of the funders they referred.
+//!
+//! A funder stays referred by its first referrer on a crowdfund, which is credited on every funding
+//! of the funder. The reward is the referral share of the fee, and follows the fee when the funding
+//! is withdrawn or transferred. Rewards can be claimed once the item is tokenized, and are forfeited
+//! when the crowdfund is refunded.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use events::{CrowdfundEvent, CrowdfundReferralRewardClaimedData};
+
+// The netto funding referred in a coin and the reward credited on its fee, in the internal unit
+#[derive(BorshDeserialize, BorshSerialize, Default, Clone, Copy)]
+pub struct ReferralAmount {
+    pub volume: Balance,
+    pub reward: Balance
+}
+
+// The referrer of a funder, and what the fundings of the funder credited it per coin
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Referral {
+    pub referrer_id: AccountId,
+    pub coins: BTreeMap<AccountId, ReferralAmount>
+}
+
+// The credit of a referrer taken back with a withdrawal in a coin, kept to restore it when the transfer fails
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReversedReferral {
+    pub referrer_id: AccountId,
+    pub volume: U128,
+    pub reward: U128
+}
+
+// What a referrer brought to a crowdfund, amounts in the internal unit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralView {
+    pub volume: U128,
+    pub reward: U128,
+
+    // Whether the reward can be claimed now
+    pub claimable: bool,
+
+    // The part of the reward that was claimed
+    pub claimed: U128
+}
+
+#[near_bindgen]
+impl Contract {
+    // Change the share of the fee credited to referrers, for fundings from now on
+    pub fn set_referral_bps(&mut self, item_index: u64, referral_bps: u16) {
+        self.assert_role(Role::Operator);
+        require!(u128::from(referral_bps) <= BPS_DENOMINATOR, "Referral share can't be more than 10000 bps.");
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.set_referral_bps(referral_bps);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_referral_bps(&self, item_index: u64) -> u16 {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_referral_bps()
+    }
+
+    // Transfer the referral rewards of the caller on a tokenized crowdfund, in the coins the fees were paid with
+    pub fn claim_referral_rewards(&mut self, item_index: u64) -> Promise {
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+
+        let referrer_id = env::predecessor_account_id();
+        let rewards = crowdfund.start_referral_claim(&referrer_id);
+
+        // Save the claim BEFORE transferring, so it can't be claimed twice
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        let mut claim_promise: Option<Promise> = None;
+        for (coin_id, reward) in rewards {
+            let coin_amount = self.to_coin_amount(&coin_id, reward);
+
+            log!("Paying {} {} of referral rewards of item {} to {}", coin_amount, coin_id, item_index, referrer_id);
+
+            let transfer = ext_ft::ext(coin_id.clone())
+                .with_attached_deposit(1)
+                .with_static_gas(Gas(5*TGAS))
+                .ft_transfer(referrer_id.clone(), U128::from(coin_amount), Some(format!("Referral rewards of item {}", item_index)))
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5*TGAS))
+                        .claim_referral_rewards_callback(item_index, referrer_id.clone(), coin_id, U128::from(reward))
+                );
+
+            claim_promise = Some(match claim_promise {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+
+        claim_promise.expect("No referral rewards to claim!")
+    }
+
+    #[private]
+    pub fn claim_referral_rewards_callback(&mut self, item_index: u64, referrer_id: AccountId, coin_id: AccountId, reward: U128, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            log!("Paying referral rewards of item {} in {} to {} failed. They can be claimed again.", item_index, coin_id, referrer_id);
+
+            let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+            crowdfund.rollback_referral_claim(&referrer_id, &coin_id, reward.0);
+            self.crowdfunds.replace(item_index, &crowdfund);
+        } else {
+            CrowdfundEvent::CrowdfundReferralRewardClaimed(vec![CrowdfundReferralRewardClaimedData {
+                item_index,
+                account_id: referrer_id,
+                coin_id: coin_id.clone(),
+                amount: U128::from(self.to_coin_amount(&coin_id, reward.0)),
+            }]).emit();
+        }
+    }
+
+    pub fn get_referral(&self, item_index: u64, account_id: AccountId) -> ReferralView {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        let (volume, reward, claimed) = crowdfund.get_referral(&account_id);
+
+        ReferralView {
+            volume: U128::from(volume),
+            reward: U128::from(reward),
+            claimable: crowdfund.get_status() == CrowdfundStatus::Tokenized && reward > claimed,
+            claimed: U128::from(claimed),
+        }
+    }
+
+    // The account that referred a funder on a crowdfund
+    pub fn get_referrer(&self, item_index: u64, account_id: AccountId) -> Option<AccountId> {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_referrer(&account_id)
+    }
+}
@@ -0,0 +1,129 @@
+//! Module for refunding funders of crowdfunds that won't be tokenized.
+
+use crate::*;
+
+use events::{CrowdfundEvent, CrowdfundCancelledData, CrowdfundRefundedData};
+
+// Every refund transfer and its callback take 10 TGAS, so batches are limited by transfers: one per funder and coin
+const DEFAULT_REFUND_TRANSFERS: u64 = 10;
+const MAX_REFUND_TRANSFERS: u64 = 20;
+
+#[near_bindgen]
+impl Contract {
+    // Cancel a crowdfund that is created or in progress, after which its funders are refunded including fees
+    pub fn cancel_crowdfund(&mut self, item_index: u64, reason: String) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.check_deadline();
+        crowdfund.transition(CrowdfundStatus::Rejected);
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        CrowdfundEvent::CrowdfundCancelled(vec![CrowdfundCancelledData {
+            item_index,
+            reason,
+        }]).emit();
+    }
+
+    // Give back the funding of the caller, in the coins it was paid with, on a crowdfund that ran out of time, was cancelled, or failed buying or transporting the item
+    pub fn claim_refund(&mut self, item_index: u64) -> Promise {
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.check_deadline();
+
+        let funder = env::predecessor_account_id();
+        let refund_amounts = crowdfund.start_refund(&funder);
+
+        // Save the refund BEFORE transferring, so it can't be claimed twice
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        self.refund_funder(item_index, &funder, refund_amounts)
+    }
+
+    // Push refunds to the next funders in line, so funders don't have to claim. The limit counts transfers, one per funder and coin.
+    // Returns the number of funders left
+    pub fn process_refunds(&mut self, item_index: u64, limit: Option<u64>) -> u64 {
+        self.assert_role(Role::Operator);
+        let limit = limit.unwrap_or(DEFAULT_REFUND_TRANSFERS);
+        require!(limit > 0 && limit <= MAX_REFUND_TRANSFERS, format!("Limit must be between 1 and {}.", MAX_REFUND_TRANSFERS));
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.check_deadline();
+        let batch = crowdfund.start_batch_refund(limit);
+
+        // Save the refunds BEFORE transferring, so they can't be claimed twice
+        self.crowdfunds.replace(item_index, &crowdfund);
+
+        // Failed transfers are rolled back in the callback, and can be claimed by the funder
+        for (funder, refund_amounts) in batch {
+            self.refund_funder(item_index, &funder, refund_amounts);
+        }
+
+        crowdfund.get_refunds_remaining()
+    }
+
+    #[private]
+    pub fn claim_refund_callback(&mut self, item_index: u64, funder: AccountId, coin_id: AccountId, refund_amount: U128, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            log!("Refund of {} {} for item {} to {} failed. It can be claimed again.", refund_amount.0, coin_id, item_index, funder);
+
+            let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+            crowdfund.rollback_refund(&funder, &coin_id);
+            self.crowdfunds.replace(item_index, &crowdfund);
+        } else {
+            log!("Refunded {} {} for item {} to {}", refund_amount.0, coin_id, item_index, funder);
+
+            // The funder gets back the storage of the funding in the coin
+            let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+            let initial_storage = env::storage_usage();
+            crowdfund.complete_refund(&funder, &coin_id);
+            self.crowdfunds.replace(item_index, &crowdfund);
+            self.release_storage(&funder, initial_storage);
+
+            CrowdfundEvent::CrowdfundRefunded(vec![CrowdfundRefundedData {
+                item_index,
+                account_id: funder,
+                coin_id,
+                amount: refund_amount,
+            }]).emit();
+        }
+    }
+
+    // The amount the account gets back (or got back) when the crowdfund is refunded
+    pub fn get_refund_amount(&self, item_index: u64, account_id: AccountId) -> U128 {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+        U128::from(crowdfund.get_refund_amount(&account_id))
+    }
+
+    pub fn is_refunded(&self, item_index: u64, account_id: AccountId) -> bool {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").is_refunded(&account_id)
+    }
+}
+
+impl Contract {
+    // Refund in every coin the funder paid with
+    fn refund_funder(&self, item_index: u64, funder: &AccountId, refund_amounts: Vec<(AccountId, u128)>) -> Promise {
+        let mut refund_promise: Option<Promise> = None;
+        for (coin_id, refund_amount) in refund_amounts {
+            let coin_amount = self.to_coin_amount(&coin_id, refund_amount);
+
+            log!("Refunding {} {} of item {} to {}", coin_amount, coin_id, item_index, funder);
+
+            let transfer = ext_ft::ext(coin_id.clone())
+                .with_attached_deposit(1)
+                .with_static_gas(Gas(5*TGAS))
+                .ft_transfer(funder.clone(), U128::from(coin_amount), Some(format!("Refund for item {}", item_index)))
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5*TGAS))
+                        .claim_refund_callback(item_index, funder.clone(), coin_id, U128::from(coin_amount))
+                );
+
+            refund_promise = Some(match refund_promise {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+
+        refund_promise.expect("Nothing to refund!")
+    }
+}
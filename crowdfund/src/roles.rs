@@ -0,0 +1,103 @@
+//! Module for access control: the owner grants roles to accounts for the privileged methods.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+// See smart contract documentation for what each role may do
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    // Configures the contract and manages the other roles. There is always exactly one owner
+    Owner,
+
+    // Creates crowdfunds and moves them through their lifecycle
+    Operator,
+
+    // Pauses and unpauses the contract
+    Pauser,
+
+    // Withdraws collected fees to the treasury
+    Treasurer,
+
+    // Maintains the allowlist of verified investors
+    Compliance
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_role(Role::Owner);
+        require!(role != Role::Owner, "Ownership can only be transferred.");
+
+        let mut members = self.get_members(role);
+        if members.insert(&account_id) {
+            log!("Granted {:?} to {}", role, account_id);
+            self.roles.insert(&role, &members);
+        }
+    }
+
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_role(Role::Owner);
+        require!(role != Role::Owner, "Ownership can only be transferred.");
+
+        let mut members = self.get_members(role);
+        if members.remove(&account_id) {
+            log!("Revoked {:?} from {}", role, account_id);
+            self.roles.insert(&role, &members);
+        }
+    }
+
+    pub fn get_role_members(&self, role: Role) -> Vec<AccountId> {
+        match role {
+            Role::Owner => vec![self.owner_id.clone()],
+            _ => self.get_members(role).to_vec(),
+        }
+    }
+
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        match role {
+            Role::Owner => account_id == self.owner_id,
+            _ => self.get_members(role).contains(&account_id),
+        }
+    }
+
+    // Propose a new owner, who becomes the owner once accepting. Proposing the current owner cancels the transfer
+    pub fn transfer_ownership(&mut self, new_owner_id: AccountId) {
+        self.assert_role(Role::Owner);
+
+        if new_owner_id == self.owner_id {
+            self.pending_owner_id = None;
+        } else {
+            log!("Proposed {} as new owner", new_owner_id);
+            self.pending_owner_id = Some(new_owner_id);
+        }
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let caller = env::predecessor_account_id();
+        require!(self.pending_owner_id.as_ref() == Some(&caller), "Caller is not the proposed owner.");
+
+        log!("Ownership transferred from {} to {}", self.owner_id, caller);
+        self.owner_id = caller;
+        self.pending_owner_id = None;
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner_id.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(self.has_role(role, env::predecessor_account_id()), format!("Caller does not have the {:?} role.", role));
+    }
+
+    fn get_members(&self, role: Role) -> UnorderedSet<AccountId> {
+        self.roles.get(&role).unwrap_or_else(|| UnorderedSet::new(StorageKeys::RoleMembers { role }))
+    }
+}
@@ -0,0 +1,85 @@
+//! Module for the versioned layout of the contract state.
+//!
+//! The version of the contract layout is kept under its own storage key, so `migrate` knows which
+//! layout to read. Crowdfunds are stored with a version tag and upgraded when they are read: adding
+//! a field to `Crowdfund` means adding a variant to `VersionedCrowdfund` with a conversion.
+//!
+//! Only layouts that were deployed need a migration: the legacy layout on testnet, and the layouts
+//! released since. Layouts that only existed during development are not kept.
+
+use crate::*;
+
+use near_sdk::IntoStorageKey;
+
+// Bump when the layout of the contract changes, and handle the previous version in migrate
+pub const STATE_VERSION: u16 = 1;
+
+const STATE_VERSION_KEY: &[u8] = b"VERSION";
+
+// State written before the layout was versioned has no version
+pub fn read_state_version() -> u16 {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|bytes| u16::try_from_slice(&bytes).expect("Invalid state version!"))
+        .unwrap_or(0)
+}
+
+pub fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+#[derive(BorshDeserialize)]
+pub enum VersionedCrowdfund {
+    V1(Crowdfund)
+}
+
+// The borsh tag of the current variant of VersionedCrowdfund, its index. Pinned to the latest variant by a test
+const CURRENT_CROWDFUND_TAG: u8 = 0;
+
+impl From<VersionedCrowdfund> for Crowdfund {
+    fn from(versioned: VersionedCrowdfund) -> Self {
+        match versioned {
+            VersionedCrowdfund::V1(crowdfund) => crowdfund,
+        }
+    }
+}
+
+// Serializes like the current variant of VersionedCrowdfund, without taking ownership of the crowdfund
+fn to_current_version(crowdfund: &Crowdfund) -> Vec<u8> {
+    let mut bytes = vec![CURRENT_CROWDFUND_TAG];
+    crowdfund.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+// The list of crowdfunds, read and written in their current layout
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CrowdfundVector {
+    crowdfunds: Vector<VersionedCrowdfund>
+}
+
+impl CrowdfundVector {
+    pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
+        Self {
+            crowdfunds: Vector::new(prefix),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.crowdfunds.len()
+    }
+
+    pub fn get(&self, item_index: u64) -> Option<Crowdfund> {
+        self.crowdfunds.get(item_index).map(Crowdfund::from)
+    }
+
+    pub fn push(&mut self, crowdfund: &Crowdfund) {
+        self.crowdfunds.push_raw(&to_current_version(crowdfund));
+    }
+
+    pub fn replace(&mut self, item_index: u64, crowdfund: &Crowdfund) {
+        self.crowdfunds.replace_raw(item_index, &to_current_version(crowdfund));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Crowdfund> + '_ {
+        self.crowdfunds.iter().map(Crowdfund::from)
+    }
+}
@@ -0,0 +1,163 @@
+//! Module for NEP-145 storage management: accounts pay for the storage of the items and fundings they add.
+
+use crate::*;
+
+use near_sdk::StorageUsage;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
+
+// The storage deposit of an account and the bytes it pays for, including its own registration
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    deposit: Balance,
+    used_bytes: StorageUsage
+}
+
+// The storage taken by registering the longest possible account id
+pub fn measure_account_storage_usage() -> StorageUsage {
+    let mut storage_accounts: LookupMap<AccountId, StorageAccount> = LookupMap::new(StorageKeys::StorageAccounts);
+    let tmp_account_id: AccountId = "a".repeat(64).parse().unwrap();
+
+    let initial_storage = env::storage_usage();
+    storage_accounts.insert(&tmp_account_id, &StorageAccount { deposit: 0, used_bytes: 0 });
+    let account_storage_usage = env::storage_usage() - initial_storage;
+    storage_accounts.remove(&tmp_account_id);
+
+    account_storage_usage
+}
+
+fn storage_cost(bytes: StorageUsage) -> Balance {
+    Balance::from(bytes) * env::storage_byte_cost()
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    // Register an account or add to its deposit. With registration only, just the minimum is kept and the rest is given back
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+
+        let mut account = match self.storage_accounts.get(&account_id) {
+            Some(_) if registration_only => {
+                log!("Account {} is already registered.", account_id);
+                if amount > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(amount);
+                }
+                return self.storage_balance_of(account_id).unwrap();
+            },
+            Some(account) => account,
+            None => {
+                let min_balance = self.storage_balance_bounds().min.0;
+                require!(amount >= min_balance, "The attached deposit is less than the minimum storage balance.");
+
+                StorageAccount { deposit: 0, used_bytes: self.account_storage_usage }
+            }
+        };
+
+        if registration_only {
+            let min_balance = self.storage_balance_bounds().min.0;
+            account.deposit = min_balance;
+            if amount > min_balance {
+                Promise::new(env::predecessor_account_id()).transfer(amount - min_balance);
+            }
+        } else {
+            account.deposit += amount;
+        }
+
+        self.storage_accounts.insert(&account_id, &account);
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    // Withdraw the part of the deposit that isn't paying for storage. Requires 1 yoctoNEAR
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self.storage_accounts.get(&account_id).expect("Account is not registered for storage.");
+
+        let available = account.deposit.saturating_sub(storage_cost(account.used_bytes));
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        require!(amount <= available, "The amount is greater than the available storage balance.");
+
+        account.deposit -= amount;
+        self.storage_accounts.insert(&account_id, &account);
+        if amount > 0 {
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    // Unregister the caller and give back its deposit. Not possible while it pays for fundings or items, which can't be removed by force
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        require!(!force.unwrap_or(false), "Force unregistering is not supported.");
+
+        let account_id = env::predecessor_account_id();
+        let account = match self.storage_accounts.get(&account_id) {
+            Some(account) => account,
+            None => {
+                log!("Account {} is not registered.", account_id);
+                return false;
+            }
+        };
+
+        require!(account.used_bytes <= self.account_storage_usage, "Can't unregister while paying for the storage of fundings or items.");
+
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id).transfer(account.deposit);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(storage_cost(self.account_storage_usage)),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts.get(&account_id).map(|account| StorageBalance {
+            total: U128::from(account.deposit),
+            available: U128::from(account.deposit.saturating_sub(storage_cost(account.used_bytes))),
+        })
+    }
+}
+
+impl Contract {
+    pub(crate) fn is_storage_registered(&self, account_id: &AccountId) -> bool {
+        self.storage_accounts.contains_key(account_id)
+    }
+
+    // Charge the account for the storage added since the initial usage. Fails when its deposit doesn't cover it
+    pub(crate) fn charge_storage(&mut self, account_id: &AccountId, initial_storage: StorageUsage) {
+        let added = env::storage_usage().saturating_sub(initial_storage);
+        if added == 0 {
+            return;
+        }
+
+        let mut account = self.storage_accounts.get(account_id).expect("Account is not registered for storage.");
+        account.used_bytes += added;
+
+        let needed = storage_cost(account.used_bytes);
+        require!(account.deposit >= needed, format!("Not enough storage deposit for {}: {} yoctoNEAR needed.", account_id, needed));
+
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    // Free the storage removed since the initial usage from what the account pays for, so it can be withdrawn
+    pub(crate) fn release_storage(&mut self, account_id: &AccountId, initial_storage: StorageUsage) {
+        let freed = initial_storage.saturating_sub(env::storage_usage());
+        if freed == 0 {
+            return;
+        }
+
+        // Storage added before the account registered was paid by the contract, and isn't given back
+        if let Some(mut account) = self.storage_accounts.get(account_id) {
+            account.used_bytes = account.used_bytes.saturating_sub(freed).max(self.account_storage_usage);
+            self.storage_accounts.insert(account_id, &account);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Module for listing crowdfunds with everything a frontend needs in a single call.
+
+use crate::*;
+
+use near_sdk::serde::Serialize;
+
+// The number of crowdfunds returned when no limit is given
+const DEFAULT_CROWDFUNDS_LIMIT: u64 = 50;
+
+// Summary of a crowdfund, amounts in the internal unit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CrowdfundView {
+    pub item_index: u64,
+    pub metadata: TokenMetadata,
+    pub status: CrowdfundStatus,
+    pub goal: U128,
+    pub hard_cap: U128,
+    pub progress: U128,
+    pub fee_bps: u16,
+    pub funders: u64,
+
+    // Timestamp (in nanoseconds) after which the crowdfund no longer accepts funding
+    pub deadline: u64,
+
+    // The id of the minted NFT, once the item is tokenized
+    pub token_id: Option<TokenId>,
+
+    pub limits: ContributionLimits,
+    pub access: Access,
+
+    // When a created crowdfund opens for funding, if scheduled
+    pub launch: Option<LaunchSchedule>
+}
+
+impl CrowdfundView {
+    fn from_crowdfund(item_index: u64, crowdfund: &Crowdfund) -> Self {
+        Self {
+            item_index,
+            metadata: crowdfund.get_metadata(),
+            status: crowdfund.get_status(),
+            goal: U128::from(crowdfund.get_goal()),
+            hard_cap: U128::from(crowdfund.get_hard_cap()),
+            progress: U128::from(crowdfund.get_progress()),
+            fee_bps: crowdfund.get_fee_bps(),
+            funders: crowdfund.get_funder_count(),
+            deadline: crowdfund.get_deadline(),
+            token_id: crowdfund.get_token_id(),
+            limits: crowdfund.get_limits(),
+            access: crowdfund.get_access(),
+            launch: crowdfund.get_launch(),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // List crowdfunds starting at from_index, optionally only those with the given status.
+    // The limit applies to the crowdfunds scanned, so a status filter may return fewer before the end.
+    pub fn get_crowdfunds(&self, from_index: Option<u64>, limit: Option<u64>, status: Option<CrowdfundStatus>) -> Vec<CrowdfundView> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_CROWDFUNDS_LIMIT);
+        require!(limit > 0, "Limit must be greater than 0.");
+
+        let mut views = vec!();
+
+        let to_index = std::cmp::min(from_index.saturating_add(limit), self.crowdfunds.len());
+        for item_index in from_index..to_index {
+            // Report the status the crowdfund would have when touched now
+            let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+
+            if status.is_none() || status == Some(crowdfund.get_status()) {
+                views.push(CrowdfundView::from_crowdfund(item_index, &crowdfund));
+            }
+        }
+
+        views
+    }
+
+    pub fn get_crowdfund(&self, item_index: u64) -> CrowdfundView {
+        let crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!").at_effective_status();
+
+        CrowdfundView::from_crowdfund(item_index, &crowdfund)
+    }
+}
@@ -0,0 +1,108 @@
+//! Module for funders withdrawing their contribution before the goal of a crowdfund is reached.
+
+use crate::*;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+use coins::CoinAmount;
+use events::{CrowdfundEvent, CrowdfundWithdrawnData};
+use referrals::ReversedReferral;
+
+// A withdrawal in a single coin, in the internal unit, handed to the callback to restore it when the transfer fails
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CoinWithdrawal {
+    pub netto: U128,
+    pub fee: U128,
+
+    // The credit of the referrer of the funder taken back with it
+    pub referral: Option<ReversedReferral>,
+
+    // When the funder last funded, restored with the funding
+    pub funded_at: Option<u64>
+}
+
+#[near_bindgen]
+impl Contract {
+    // Only allow withdrawing within this many nanoseconds after funding. Without a window, withdrawing is possible until the goal is reached
+    pub fn set_withdrawal_window(&mut self, item_index: u64, withdrawal_window: Option<u64>) {
+        self.assert_role(Role::Operator);
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.set_withdrawal_window(withdrawal_window);
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+
+    pub fn get_withdrawal_window(&self, item_index: u64) -> Option<u64> {
+        self.crowdfunds.get(item_index).expect("Incorrect item index!").get_withdrawal_window()
+    }
+
+    // Give back part of the funding of the caller (netto, in the internal unit) with the fee paid on it, in the coins it was paid with
+    pub fn withdraw_contribution(&mut self, item_index: u64, amount: U128) -> Promise {
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        crowdfund.check_deadline();
+
+        let funder = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let funded_at = crowdfund.get_funded_at(&funder);
+        let withdrawals = crowdfund.start_withdrawal(&funder, amount.0);
+
+        // Save the withdrawal BEFORE transferring, so it can't be withdrawn twice
+        self.crowdfunds.replace(item_index, &crowdfund);
+        self.release_storage(&funder, initial_storage);
+
+        let mut withdrawal_promise: Option<Promise> = None;
+        for (coin_id, coin_amount, referral) in withdrawals {
+            let transfer_amount = self.to_coin_amount(&coin_id, coin_amount.netto + coin_amount.fee);
+
+            log!("Withdrawing {} {} of item {} to {}", transfer_amount, coin_id, item_index, funder);
+
+            let transfer = ext_ft::ext(coin_id.clone())
+                .with_attached_deposit(1)
+                .with_static_gas(Gas(5*TGAS))
+                .ft_transfer(funder.clone(), U128::from(transfer_amount), Some(format!("Withdrawal from item {}", item_index)))
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5*TGAS))
+                        .withdraw_contribution_callback(item_index, funder.clone(), coin_id, CoinWithdrawal {
+                            netto: U128::from(coin_amount.netto),
+                            fee: U128::from(coin_amount.fee),
+                            referral,
+                            funded_at,
+                        })
+                );
+
+            withdrawal_promise = Some(match withdrawal_promise {
+                Some(promise) => promise.and(transfer),
+                None => transfer,
+            });
+        }
+
+        withdrawal_promise.expect("Nothing to withdraw!")
+    }
+
+    #[private]
+    pub fn withdraw_contribution_callback(&mut self, item_index: u64, funder: AccountId, coin_id: AccountId, withdrawal: CoinWithdrawal, #[callback_result] call_result: Result<(), PromiseError>) {
+        let transfer_amount = self.to_coin_amount(&coin_id, withdrawal.netto.0 + withdrawal.fee.0);
+        let coin_amount = CoinAmount { netto: withdrawal.netto.0, fee: withdrawal.fee.0 };
+
+        let mut crowdfund = self.crowdfunds.get(item_index).expect("Incorrect item index!");
+        if call_result.is_err() {
+            log!("Withdrawal of {} {} from item {} to {} failed. The funding is restored.", transfer_amount, coin_id, item_index, funder);
+
+            // The contract pays for the storage of the restored funding, as a callback shouldn't fail on it
+            crowdfund.rollback_withdrawal(&funder, &coin_id, coin_amount, withdrawal.referral, withdrawal.funded_at);
+        } else {
+            log!("Withdrew {} {} from item {} to {}", transfer_amount, coin_id, item_index, funder);
+            crowdfund.complete_withdrawal(coin_amount);
+
+            CrowdfundEvent::CrowdfundWithdrawn(vec![CrowdfundWithdrawnData {
+                item_index,
+                account_id: funder,
+                coin_id,
+                amount: U128::from(transfer_amount),
+            }]).emit();
+        }
+        self.crowdfunds.replace(item_index, &crowdfund);
+    }
+}
@@ -24,6 +24,7 @@ import CrowdFundModal, { useCrowdFundModal } from "../CrowdFundModal";
 import { useState, useEffect } from "react";
 
 const IPFS_HTTPS_PREFIX = "https://ipfs.io/ipfs/";
+const CROWDFUNDS_PAGE_SIZE = 50;
 
 const TradeData = [
   {
@@ -123,34 +124,23 @@ const Markets = (props) => {
   useEffect(() => {
     if (props.wallet) {
       const fetchCrowdfunds = async () => {
-        // Get all crowdfunds and update state
-        let currentCrowdfunds = await props.wallet.getCurrentCrowdfunds();
-        setCrowdfunds(currentCrowdfunds);
+        // Get all crowdfunds with their progress and goal, page by page until a page is not full, and update state
+        let currentCrowdfunds = [];
+        let page = [];
+        do {
+          page = await props.wallet.getCrowdfunds(currentCrowdfunds.length, CROWDFUNDS_PAGE_SIZE);
+          currentCrowdfunds = currentCrowdfunds.concat(page);
+        } while (page.length == CROWDFUNDS_PAGE_SIZE);
+
+        setCrowdfunds(currentCrowdfunds.map(crowdfund => crowdfund.metadata));
+        setCrowdfundProgresses(currentCrowdfunds.map(crowdfund => crowdfund.progress));
+        setCrowdfundGoals(currentCrowdfunds.map(crowdfund => crowdfund.goal));
       }
 
       fetchCrowdfunds();
     }
   }, [])
 
-  // Get all crowdfund progresses on crowdfund change
-  useEffect(() => {
-    if (crowdfunds) {
-      const loadProgresses = async () => {
-        if (props.wallet) {
-          for (let crowdfund in crowdfunds) {
-            let currentProgress = await props.wallet.getCrowdfundProgress(parseInt(crowdfund));
-            let currentGoal = await props.wallet.getCrowdfundGoal(parseInt(crowdfund));
-
-            setCrowdfundProgresses(oldProgresses => [...oldProgresses, currentProgress]);
-            setCrowdfundGoals(oldGoals => [...oldGoals, currentGoal]);
-          }
-        }
-      }
-
-      loadProgresses();
-    }
-  }, [crowdfunds]);
-
   // Calculate the accountName from the name
   // TODO disregard account name
   useEffect(() => {
@@ -14,8 +14,14 @@ import { setupMyNearWallet } from '@near-wallet-selector/my-near-wallet';
 
 const THIRTY_TGAS = '30000000000000';
 const NO_DEPOSIT = '0';
+const DEFAULT_CROWDFUND_DURATION_MS = 30 * 24 * 60 * 60 * 1000;  // 30 days
 const FAKE_USDC_CONTRACT = "usdc.fakes.testnet";
 
+// The crowdfund contract charges the storage of fundings and items to the account adding them (NEP-145).
+// Funding or creating is rejected when the storage deposit can't cover it, so it's topped up first
+const FUNDING_STORAGE_DEPOSIT = '10000000000000000000000';  // 0.01 N
+const ITEM_STORAGE_DEPOSIT = '100000000000000000000000';  // 0.1 N
+
 // Wallet that simplifies using the wallet selector
 export class Wallet {
   walletSelector;
@@ -86,13 +92,53 @@ export class Wallet {
       "reference": metadataUrl
     };
 
-    return await this.callMethod({contractId: this.crowdfundsContractId, method: 'new_item', args:{item_metadata: token_metadata, goal: goal}});
+    // The crowdfund deadline is expressed in nanoseconds
+    let end_timestamp = (Date.now() + DEFAULT_CROWDFUND_DURATION_MS) * 1000000;
+
+    // The operator pays for the storage of the item, in the same transaction
+    let actions = [];
+    let storageDeposit = await this.getStorageTopUp(ITEM_STORAGE_DEPOSIT);
+    if (storageDeposit !== null) {
+      actions.push(this.functionCall('storage_deposit', {}, THIRTY_TGAS, storageDeposit));
+    }
+    actions.push(this.functionCall('new_item', {item_metadata: token_metadata, goal: goal, end_timestamp: end_timestamp}, THIRTY_TGAS, NO_DEPOSIT));
+
+    return await this.wallet.signAndSendTransaction({signerId: this.accountId, receiverId: this.crowdfundsContractId, actions: actions});
+  }
+
+  // The deposit to attach to storage_deposit so the available storage balance covers the amount, or null when it already does.
+  // Unregistered accounts pay at least the minimum balance of the registration
+  async getStorageTopUp(amount) {
+    let balance = await this.viewMethod({contractId: this.crowdfundsContractId, method: 'storage_balance_of', args:{account_id: this.accountId}});
+    let needed = BigInt(amount);
+
+    if (balance === null) {
+      let bounds = await this.viewMethod({contractId: this.crowdfundsContractId, method: 'storage_balance_bounds'});
+      return (needed + BigInt(bounds.min)).toString();
+    }
+
+    let available = BigInt(balance.available);
+    return available >= needed ? null : (needed - available).toString();
   }
 
   async getCurrentCrowdfunds() {
     return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_current_items'});
   }
 
+  // Crowdfunds with their status, goal, progress, ... in a single call
+  async getCrowdfunds(fromIndex, limit, status) {
+    return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_crowdfunds', args:{from_index: fromIndex, limit: limit, status: status}});
+  }
+
+  // Every crowdfund an account contributed to, with the amounts and refund state
+  async getContributions(accountId) {
+    return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_contributions', args:{account_id: accountId}});
+  }
+
+  async getFunders(itemIndex, fromIndex, limit) {
+    return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_funders', args:{item_index: itemIndex, from_index: fromIndex, limit: limit}});
+  }
+
   async getCrowdfundProgress(itemIndex){
     return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_crowdfund_progress', args:{item_index: itemIndex}});
   }
@@ -101,8 +147,24 @@ export class Wallet {
     return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_crowdfund_goal', args:{item_index: itemIndex}});
   }
 
+  async getCrowdfundDeadline(itemIndex) {
+    return await this.viewMethod({contractId: this.crowdfundsContractId, method: 'get_crowdfund_deadline', args:{item_index: itemIndex}});
+  }
+
+  async claimRefund(itemIndex) {
+    return await this.callMethod({contractId: this.crowdfundsContractId, method: 'claim_refund', args:{item_index: itemIndex}, gas: '300000000000000'});
+  }
+
+  // Register for storage before funding, a funding from an account without storage deposit is refunded
   async fundUSDC(itemIndex, amount) {
-    return await this.callMethod({contractId: FAKE_USDC_CONTRACT, method: 'ft_transfer_call', args:{receiver_id: this.crowdfundsContractId, amount: amount, memo: "funding", msg: itemIndex}, gas: '300000000000000', deposit: '1'})
+    let transactions = [];
+    let storageDeposit = await this.getStorageTopUp(FUNDING_STORAGE_DEPOSIT);
+    if (storageDeposit !== null) {
+      transactions.push({receiverId: this.crowdfundsContractId, actions: [this.functionCall('storage_deposit', {}, THIRTY_TGAS, storageDeposit)]});
+    }
+    transactions.push({receiverId: FAKE_USDC_CONTRACT, actions: [this.functionCall('ft_transfer_call', {receiver_id: this.crowdfundsContractId, amount: amount, memo: "funding", msg: JSON.stringify({item_index: parseInt(itemIndex)})}, '300000000000000', '1')]});
+
+    return await this.wallet.signAndSendTransactions({transactions: transactions});
   }
 
   // --------- ITEMS ---------
@@ -172,20 +234,23 @@ export class Wallet {
     return await this.wallet.signAndSendTransaction({
       signerId: accountId,
       receiverId: contractId,
-      actions: [
-        {
-          type: 'FunctionCall',
-          params: {
-            methodName: method,
-            args,
-            gas,
-            deposit,
-          },
-        },
-      ],
+      actions: [this.functionCall(method, args, gas, deposit)],
     });
   }
 
+  // A "FunctionCall" action, to sign alone or together with others
+  functionCall(method, args, gas, deposit) {
+    return {
+      type: 'FunctionCall',
+      params: {
+        methodName: method,
+        args,
+        gas,
+        deposit,
+      },
+    };
+  }
+
   // Get transaction result from the network
   async getTransactionResult(txhash) {
     const { network } = this.walletSelector.options;
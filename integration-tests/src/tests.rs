@@ -1,4 +1,5 @@
 use std::{env, fs};
+use std::time::{SystemTime, UNIX_EPOCH};
 use near_units::parse_near;
 use serde_json::json;
 use workspaces::prelude::*;
@@ -8,6 +9,10 @@ use near_sdk::AccountId;
 
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
 
+// Decimals of fake usdc, and of the internal unit the crowdfund contract accounts in
+const FUSDC_DECIMALS: u32 = 24;
+const CROWDFUND_DECIMALS: u32 = 6;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // ---------------- ARRANGE ----------------
@@ -59,7 +64,8 @@ async fn main() -> anyhow::Result<()> {
     wehave_account.call(&worker, crowdfund_contract.id(), "new")
         .args_json(json!({
             "nft_account_id": nft_account_id,
-            "accepted_coin": accepted_coin
+            "accepted_coin": accepted_coin,
+            "accepted_coin_decimals": FUSDC_DECIMALS
         }))?
         .transact()
         .await?;
@@ -106,7 +112,7 @@ async fn main() -> anyhow::Result<()> {
     wehave_account.call(&worker, fusdc_contract.id(), "new_default_meta")
         .args_json(serde_json::json!({
             "owner_id": wehave_id,      // WeHave.test.near gets all the supply
-            "total_supply": U128::from(1000000)
+            "total_supply": to_fusdc(1000000)
         }))?
         .transact()
         .await?;
@@ -137,6 +143,10 @@ async fn main() -> anyhow::Result<()> {
     let bob_id: AccountId = "bob.test.near".parse().unwrap();
     distribute_fusdc(&worker, &fusdc_contract, &wehave_account, &bob_id).await?;
 
+    // Alice and Bob pay for the storage of the items and fundings they add to the crowdfund contract
+    register_crowdfund_storage(&worker, &crowdfund_contract, &alice).await?;
+    register_crowdfund_storage(&worker, &crowdfund_contract, &bob).await?;
+
     println!("Alice becomes a crowdfund operator");
     add_crowdfund_operator(&worker, &crowdfund_contract, &crowdfund_account, &alice_id).await?;
 
@@ -145,13 +155,23 @@ async fn main() -> anyhow::Result<()> {
     crowdfund_new_item(&worker, &crowdfund_contract, &alice, String::from("ferrari"), 1000).await?;
     println!("Alice funds the ferrari for 400 usdc");
     // Alice funds the item
-    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &alice, String::from("0"), String::from("400")).await?;
+    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &alice, String::from("0"), 400).await?;
 
     println!("Bob funds the ferrari for 700 usdc");
     // Bob funds the item
-    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &bob, String::from("0"), String::from("700")).await?;
+    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &bob, String::from("0"), 700).await?;
+
+    println!("Alice buys, transports and tokenizes the ferrari");
+    // Alice moves the crowdfund through buying and transporting, and tokenizes once in the warehouse
+    for method in ["start_buying", "start_transporting", "confirm_in_warehouse", "tokenize"] {
+        operate_crowdfund(&worker, &crowdfund_contract, &alice, method, 0).await?;
+    }
 
-    // FT should be created, distributed & DAO should be created
+    // FT & DAO should be created, the funders claim their share of the FT
+    let ferrari_ft_id: workspaces::AccountId = "item0.nft.test.near".parse()?;
+    println!("Alice and Bob claim their ferrari tokens");
+    claim_item_tokens(&worker, &alice, &ferrari_ft_id).await?;
+    claim_item_tokens(&worker, &bob, &ferrari_ft_id).await?;
 
     println!("Bob becomes a crowdfund operator");
     add_crowdfund_operator(&worker, &crowdfund_contract, &crowdfund_account, &bob_id).await?;
@@ -160,11 +180,11 @@ async fn main() -> anyhow::Result<()> {
     crowdfund_new_item(&worker, &crowdfund_contract, &bob, String::from("rolex"), 2000).await?;
     println!("Alice funds the ferrari for 400 usdc");
     // Alice funds the item
-    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &alice, String::from("1"), String::from("400")).await?;
+    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &alice, String::from("1"), 400).await?;
 
     println!("Bob funds the ferrari for 1900 usdc");
     // Bob funds the item
-    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &bob, String::from("1"), String::from("1900")).await?;
+    fund_item(&worker, &fusdc_contract, &crowdfund_contract, &bob, String::from("1"), 1900).await?;
 
 
     println!("Creating new proposal for ferrari.");
@@ -189,6 +209,11 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+// Convert an amount in the crowdfund's internal unit to fake usdc
+fn to_fusdc(amount: u128) -> U128 {
+    U128::from(amount * 10u128.pow(FUSDC_DECIMALS - CROWDFUND_DECIMALS))
+}
+
 async fn distribute_fusdc(worker: &Worker<Sandbox>, contract: &Contract, user: &Account, to: &AccountId) -> anyhow::Result<()> {
     // Register the user by storage deposit
     let result = user.call(&worker, contract.id(), "storage_deposit")
@@ -198,8 +223,10 @@ async fn distribute_fusdc(worker: &Worker<Sandbox>, contract: &Contract, user: &
         .transact()
         .await?;
 
+    assert!(result.is_success());
+
     let result = user.call(&worker, contract.id(), "ft_transfer")
-        .args_json(json!({"receiver_id": to, "amount": U128::from(3000)}))?
+        .args_json(json!({"receiver_id": to, "amount": to_fusdc(3000)}))?
         .max_gas()
         .deposit(parse_near!("1 yN"))
         .transact()
@@ -210,9 +237,22 @@ async fn distribute_fusdc(worker: &Worker<Sandbox>, contract: &Contract, user: &
     Ok(())
 }
 
+async fn register_crowdfund_storage(worker: &Worker<Sandbox>, contract: &Contract, user: &Account) -> anyhow::Result<()> {
+    let result = user.call(&worker, contract.id(), "storage_deposit")
+        .args_json(json!({}))?
+        .max_gas()
+        .deposit(parse_near!("1 N"))
+        .transact()
+        .await?;
+
+    assert!(result.is_success());
+
+    Ok(())
+}
+
 async fn add_crowdfund_operator(worker: &Worker<Sandbox>, contract: &Contract, cfUser: &Account, operator: &AccountId) -> anyhow::Result<()> {
-    let result = cfUser.call(&worker, contract.id(), "add_operator")
-        .args_json(json!({"operator": operator}))?
+    let result = cfUser.call(&worker, contract.id(), "grant_role")
+        .args_json(json!({"role": "Operator", "account_id": operator}))?
         .transact()
         .await?;
 
@@ -235,12 +275,45 @@ async fn crowdfund_new_item(worker: &Worker<Sandbox>, contract: &Contract, user:
             reference_hash: None,
         };
 
+    // Crowdfund runs for 30 days
+    let end_timestamp = (SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + 30 * 24 * 60 * 60) * 1_000_000_000;
 
     let result = user.call(&worker, contract.id(), "new_item")
-        .args_json(json!({"item_metadata": token_metadata, "goal": goal}))?
+        .args_json(json!({"item_metadata": token_metadata, "goal": goal, "end_timestamp": end_timestamp}))?
+        .transact()
+        .await?;
+
+    // The storage of the item is paid from the deposit of the operator
+    assert!(result.is_success());
+
+    Ok(())
+}
+
+async fn operate_crowdfund(worker: &Worker<Sandbox>, contract: &Contract, operator: &Account, method: &str, item_index: u64) -> anyhow::Result<()> {
+    let result = operator.call(&worker, contract.id(), method)
+        .args_json(json!({"item_index": item_index}))?
+        .max_gas()
         .transact()
         .await?;
 
+    println!("{}: {:?}", method, result.logs());
+    assert!(result.is_success());
+
+    Ok(())
+}
+
+async fn claim_item_tokens(worker: &Worker<Sandbox>, user: &Account, ft_id: &workspaces::AccountId) -> anyhow::Result<()> {
+    let result = user.call(&worker, ft_id, "claim")
+        .args_json(json!({}))?
+        .max_gas()
+        .transact()
+        .await?;
+
+    println!("claim: {:?}", result.logs());
+
+    let balance: U128 = worker.view(ft_id, "ft_balance_of", json!({"account_id": user.id()}).to_string().into_bytes()).await?.json()?;
+    assert!(balance.0 > 0);
+
     Ok(())
 }
 
@@ -254,7 +327,7 @@ async fn list_crowdfunds(worker: &Worker<Sandbox>, contract: &Contract, user: &A
     Ok(())
 }
 
-async fn fund_item(worker: &Worker<Sandbox>, fusdc_contract: &Contract, crowdfund_contract: &Contract, user: &Account, item_index: String, amount: String) -> anyhow::Result<()> {
+async fn fund_item(worker: &Worker<Sandbox>, fusdc_contract: &Contract, crowdfund_contract: &Contract, user: &Account, item_index: String, amount: u128) -> anyhow::Result<()> {
     let crowdfund_id: AccountId = "crowdfund.test.near".parse().unwrap();
 
     // Register the user by storage deposit
@@ -268,9 +341,9 @@ async fn fund_item(worker: &Worker<Sandbox>, fusdc_contract: &Contract, crowdfun
     let result = user.call(&worker, fusdc_contract.id(), "ft_transfer_call")
         .args_json(json!({
             "receiver_id": crowdfund_id,
-            "amount": amount,
+            "amount": to_fusdc(amount),
             "memo": "funding the ferrari",
-            "msg": item_index
+            "msg": json!({"item_index": item_index.parse::<u64>()?}).to_string()
         }))?
         .max_gas()
         .deposit(parse_near!("1 yN"))
@@ -0,0 +1,95 @@
+use std::{env, fs};
+use serde_json::json;
+use workspaces::{network::Sandbox, AccountId, Contract, Worker};
+use near_sdk::json_types::U128;
+
+// The crowdfund deployment with state from before the state was versioned
+const LEGACY_CROWDFUND_ACCOUNT: &str = "crowdfunds3-wehave.testnet";
+
+// Crowdfund state version written by migrate
+const STATE_VERSION: u16 = 1;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // ---------------- ARRANGE ----------------
+
+    // Read the new crowdfund WASM from cmd line
+    let wasm_arg: &str = &(env::args().nth(1).unwrap());
+    let wasm_filepath = fs::canonicalize(env::current_dir()?.join(wasm_arg))?;
+    let crowdfund_wasm = std::fs::read(wasm_filepath)?;
+
+    // Create a sandbox (workspace), and import the legacy crowdfund contract with its state from testnet
+    let worker = workspaces::sandbox().await?;
+    let testnet = workspaces::testnet().await?;
+
+    let legacy_account_id: AccountId = LEGACY_CROWDFUND_ACCOUNT.parse()?;
+    let contract = worker.import_contract(&legacy_account_id, &testnet)
+        .with_data()
+        .transact()
+        .await?;
+
+    // Remember the legacy crowdfunds through the legacy views
+    let legacy_items: Vec<serde_json::Value> = contract.view(&worker, "get_current_items", json!({}).to_string().into_bytes()).await?.json()?;
+    let mut legacy_progresses: Vec<u128> = Vec::new();
+    for item_index in 0..legacy_items.len() {
+        let progress: u128 = contract.view(&worker, "get_crowdfund_progress", json!({"item_index": item_index}).to_string().into_bytes()).await?.json()?;
+        legacy_progresses.push(progress);
+    }
+
+    // ---------------- ACT ----------------
+
+    test_upgrade(&worker, &contract, crowdfund_wasm).await?;
+
+    // ---------------- ASSERT ----------------
+
+    test_legacy_state_survived(&worker, &contract, &legacy_items, &legacy_progresses).await?;
+
+    Ok(())
+}
+
+async fn test_upgrade(worker: &Worker<Sandbox>, contract: &Contract, crowdfund_wasm: Vec<u8>) -> anyhow::Result<()> {
+    // The legacy contract has no upgrade method: deploy the new code to its account and migrate the state
+    let deployment = contract.as_account().deploy(&worker, &crowdfund_wasm).await?;
+    assert!(deployment.is_success());
+
+    // Legacy crowdfunds that were minted are passed as minted_items, with the token minted for each
+    let result = contract.call(&worker, "migrate")
+        .args_json(json!({}))?
+        .max_gas()
+        .transact()
+        .await?;
+
+    println!("{:?}", result.logs());
+    assert!(result.is_success());
+
+    let state_version: u16 = contract.view(&worker, "get_state_version", json!({}).to_string().into_bytes()).await?.json()?;
+    assert_eq!(state_version, STATE_VERSION);
+
+    // The contract is its own owner after migrating, so from now on it can upgrade itself
+    let result = contract.call(&worker, "upgrade")
+        .args(crowdfund_wasm)
+        .max_gas()
+        .transact()
+        .await?;
+
+    println!("{:?}", result.logs());
+    assert!(result.is_success());
+
+    println!("      Passed ✅ upgrade legacy crowdfund contract");
+    Ok(())
+}
+
+async fn test_legacy_state_survived(worker: &Worker<Sandbox>, contract: &Contract, legacy_items: &Vec<serde_json::Value>, legacy_progresses: &Vec<u128>) -> anyhow::Result<()> {
+    let crowdfunds: Vec<serde_json::Value> = contract.view(&worker, "get_crowdfunds", json!({"limit": legacy_items.len().max(1)}).to_string().into_bytes()).await?.json()?;
+    assert_eq!(crowdfunds.len(), legacy_items.len());
+
+    for (item_index, crowdfund) in crowdfunds.iter().enumerate() {
+        assert_eq!(crowdfund["metadata"], legacy_items[item_index]);
+
+        let progress: U128 = serde_json::from_value(crowdfund["progress"].clone())?;
+        assert_eq!(progress.0, legacy_progresses[item_index]);
+    }
+
+    println!("      Passed ✅ legacy crowdfunds survived the upgrade");
+    Ok(())
+}
@@ -122,8 +122,8 @@ impl Contract {
         &mut self,
         token_metadata: TokenMetadata,
         ft_supply: U128,
-        holders: Vec<AccountId>,
-        shares: Vec<U128>
+        item_index: u64,
+        total_funding: U128
     ) -> Promise {
         log!("Arrived at nft_mint");
         // Only contract owner is allowed to mint (caller = owner)
@@ -160,7 +160,8 @@ impl Contract {
             .transfer(DEFAULT_TOKEN_STORAGE) // Transfer some NEAR for storage from the NFT contract itself
             .function_call(
                 String::from("new"),
-                json!({"total_supply": self.calculate_total_supply(ft_supply, DEFAULT_FT_DECIMALS), "holders": holders, "shares": shares, "metadata": ft_metadata})
+                // Funders claim their tokens from the FT, which looks up their share in the crowdfund (the caller)
+                json!({"total_supply": self.calculate_total_supply(ft_supply, DEFAULT_FT_DECIMALS), "crowdfund_account_id": env::predecessor_account_id(), "item_index": item_index, "total_funding": total_funding, "metadata": ft_metadata})
                     .to_string()
                     .as_bytes()
                     .to_vec(),
@@ -174,18 +175,18 @@ impl Contract {
             )
     }
 
-    #[handle_result]
+    // Returns the id of the minted token, so the crowdfund can keep track of it
     #[private]
     #[payable]
-    pub fn ft_deploy_callback(&mut self, token_id: TokenId, owner_id: AccountId, token_metadata: TokenMetadata, #[callback_result] call_result: Result<(), PromiseError>) {
+    pub fn ft_deploy_callback(&mut self, token_id: TokenId, owner_id: AccountId, token_metadata: TokenMetadata, #[callback_result] call_result: Result<(), PromiseError>) -> TokenId {
         if call_result.is_err() {
-            log!("Could not deploy {:?}", owner_id);
-            // Potentially give back fundings here...
+            // Fail the mint, so the crowdfund can retry or abandon the tokenization
+            env::panic_str(&format!("Could not deploy {:?}", owner_id));
         } else {
             log!("Minting item {} for ft account {}", token_id, owner_id);
 
             // Add to collection: Mint new item owned by fungible token
-            self.tokens.internal_mint(token_id, owner_id.clone(), Some(token_metadata));
+            self.tokens.internal_mint(token_id.clone(), owner_id.clone(), Some(token_metadata));
             self.token_id_counter = self.token_id_counter + 1;
 
             // Deploy dao for token
@@ -216,6 +217,8 @@ impl Contract {
                     .with_static_gas(Gas(1*TGAS))
                     .item_dao_deploy_callback()
                 );
+
+            token_id
         }
     }
 
@@ -317,12 +320,9 @@ mod tests {
         let decimals: u8 = 8;
         assert_eq!(contract.calculate_total_supply(total_supply, decimals), U128::from(100000000000000));
 
-        let ft_name = String::from("test");
         let ft_supply = U128::from(1000000);
-        let holders: Vec<AccountId> = vec!("alice.test.near".parse().unwrap());
-        let shares = vec!(1000000.into());
+        let total_funding = U128::from(1000000);
 
-        let token_id = "0".to_string();
-        let token = contract.nft_mint(token_id.clone(), sample_token_metadata(), ft_name, ft_supply, holders, shares);
+        let token = contract.nft_mint(sample_token_metadata(), ft_supply, 0, total_funding);
     }
 }